@@ -95,3 +95,43 @@ fn test_remove_subree_with_two_layers() {
     // someone might still use it
     assert!(child2.read().unwrap().children.is_empty());
 }
+
+#[test]
+fn test_attach_subtree() {
+    let mut tree = Tree::new();
+    let root = tree.create_node_and_set_root(0).unwrap();
+    let child = Tree::attach_child(&root, 1);
+    let child2 = Tree::attach_child(&child, 2);
+    assert!(tree.remove_subtree(&child).is_ok());
+
+    assert!(Tree::attach_subtree(&root, child.clone()).is_ok());
+
+    assert_eq!(root.read().unwrap().children.len(), 1);
+    assert_eq!(
+        child
+            .read()
+            .unwrap()
+            .parent
+            .as_ref()
+            .unwrap()
+            .upgrade()
+            .unwrap()
+            .read()
+            .unwrap()
+            .data,
+        0
+    );
+    // The subtree stays untouched.
+    assert_eq!(child.read().unwrap().children.len(), 1);
+    assert!(child2.read().unwrap().parent.is_some());
+}
+
+#[test]
+fn test_attach_subtree_with_parent() {
+    let mut tree = Tree::new();
+    let root = tree.create_node_and_set_root(0).unwrap();
+    let child = Tree::attach_child(&root, 1);
+
+    assert!(Tree::attach_subtree(&root, child).is_err());
+    assert_eq!(root.read().unwrap().children.len(), 1);
+}
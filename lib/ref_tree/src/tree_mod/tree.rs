@@ -91,6 +91,40 @@ impl<T> Tree<T> {
         child
     }
 
+    /// Attaches a node, which is not part of any tree, together with its
+    /// whole subtree to the given parent. E.g., a subtree removed by
+    /// [`Tree::remove_subtree`].
+    ///
+    /// Connects both parent -> child and child -> parent.
+    ///
+    /// # Errors
+    /// When the node still has a parent.
+    ///
+    /// # Panics
+    /// When could not write to parent or node, see `RwLock`.
+    pub fn attach_subtree(
+        parent: &Arc<RwLock<Node<T>>>,
+        node: Arc<RwLock<Node<T>>>,
+    ) -> Result<(), &'static str> {
+        {
+            let mut node = node
+                .write()
+                .expect("Could not write to node while attaching subtree");
+            if node.parent.is_some() {
+                return Err("Node to be attached still has a parent.");
+            }
+            node.attach_parent(parent);
+        }
+
+        parent
+            .write()
+            .expect("Could not write to parent while attaching subtree")
+            .children
+            .push(node);
+
+        Ok(())
+    }
+
     /// Data/node will be freed from memory when the callee drops the
     /// reference if last (see Arc).  This function just drops the
     /// reference of the parent of this tree and sets root to node if
@@ -0,0 +1,287 @@
+//! Deleting entries on a background thread while keeping the tree in sync
+//! with the file system.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use ref_tree::{Node, Tree};
+
+use crate::ui::event_handling::DiskoEvent;
+
+use super::{
+    disko_tree::{BackpropOperation, DeletionMode, DiskoTree},
+    entry_node::{EntryNode, EntryType},
+    entry_size::EntrySize,
+    trash::TrashedEntry,
+    traversal_options::TraversalOptions,
+    tree_walk_state::TraversalErrors,
+};
+
+/// How often the progress of the deletion is reported at most.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Entries moved to the trash by a single deletion, which can be restored.
+#[derive(Default)]
+pub struct DeletionBatch {
+    pub(super) entries: Vec<DeletedEntry>,
+}
+
+pub(super) struct DeletedEntry {
+    pub(super) trashed: TrashedEntry,
+    pub(super) node: Arc<RwLock<Node<EntryNode>>>,
+    pub(super) parent: Arc<RwLock<Node<EntryNode>>>,
+}
+
+impl DeletionBatch {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// An entry which could not be deleted.
+pub struct DeletionFailure {
+    pub path: PathBuf,
+    pub error: io::Error,
+}
+
+/// How much has been deleted so far.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DeletionProgress {
+    pub files_removed: usize,
+    pub freed: EntrySize,
+}
+
+/// The outcome of deleting multiple entries.
+#[derive(Default)]
+pub struct DeletionReport {
+    /// The entries moved to the trash.
+    pub batch: DeletionBatch,
+    pub failures: Vec<DeletionFailure>,
+    pub progress: DeletionProgress,
+    pub cancelled: bool,
+}
+
+/// The outcome of undoing a deletion.
+pub struct RestoreReport {
+    pub restored: usize,
+    /// The entries which could not be restored, so that they can be tried
+    /// again.
+    pub failed: DeletionBatch,
+    /// Why the entries could not be restored or attached to the tree, one
+    /// error for each of them.
+    pub errors: Vec<anyhow::Error>,
+}
+
+/// Deletes the given entries, detaching their nodes from the tree as they
+/// vanish from the file system.
+pub(super) struct DeletionWorker {
+    pub(super) tree: Arc<RwLock<Tree<EntryNode>>>,
+    pub(super) entries: Vec<Arc<RwLock<Node<EntryNode>>>>,
+    pub(super) deletion_mode: DeletionMode,
+    pub(super) traversal_threads: usize,
+    pub(super) traversal_options: Arc<TraversalOptions>,
+    pub(super) traversal_errors: TraversalErrors,
+    pub(super) cancel: Arc<AtomicBool>,
+    pub(super) sender: mpsc::Sender<DiskoEvent>,
+}
+
+impl DeletionWorker {
+    pub(super) fn run(self) -> DeletionReport {
+        let mut report = DeletionReport::default();
+        let mut last_progress = Instant::now();
+
+        for child in &self.entries {
+            if self.is_cancelled() {
+                break;
+            }
+            let (data, parent) = {
+                let child = child
+                    .read()
+                    .expect("Failed to read child while deleting children.");
+                (
+                    child.data.clone(),
+                    child.get_parent().and_then(|parent| parent.upgrade()),
+                )
+            };
+            // The root cannot be deleted.
+            let Some(parent) = parent else {
+                continue;
+            };
+
+            let mut progress = Progress {
+                report: &mut report.progress,
+                sender: &self.sender,
+                last_sent: &mut last_progress,
+            };
+            match self.delete(child, &data, &mut progress) {
+                Ok(Some(trashed)) => report.batch.entries.push(DeletedEntry {
+                    trashed,
+                    node: child.clone(),
+                    parent,
+                }),
+                Ok(None) => {}
+                Err(error) => {
+                    self.recover(child, &parent, &data);
+                    if !self.is_cancelled() {
+                        report.failures.push(DeletionFailure {
+                            path: data.path,
+                            error,
+                        });
+                    }
+                }
+            }
+        }
+
+        report.cancelled = self.is_cancelled();
+        // Here we just ignore if the event handler has stopped.
+        let _ = self.sender.send(DiskoEvent::DeletionFinished);
+        report
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn delete(
+        &self,
+        node: &Arc<RwLock<Node<EntryNode>>>,
+        data: &EntryNode,
+        progress: &mut Progress,
+    ) -> io::Result<Option<TrashedEntry>> {
+        if let (DeletionMode::Permanent, EntryType::Directory) =
+            (self.deletion_mode, data.entry_type)
+        {
+            self.remove_dir(Some(node), &data.path, progress)?;
+            return Ok(None);
+        }
+
+        let trashed = data.delete_entry(self.deletion_mode)?;
+        let files = count_files(node);
+        let freed = self.detach(node);
+        progress.add(files, freed);
+        Ok(trashed)
+    }
+
+    /// Remove the directory and its contents one by one, so the progress can
+    /// be reported and the deletion cancelled.
+    fn remove_dir(
+        &self,
+        node: Option<&Arc<RwLock<Node<EntryNode>>>>,
+        path: &Path,
+        progress: &mut Progress,
+    ) -> io::Result<()> {
+        for entry in fs::read_dir(path)? {
+            if self.is_cancelled() {
+                return Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "Deletion cancelled",
+                ));
+            }
+            let entry = entry?;
+            let child = node.and_then(|node| DiskoTree::find_child(node, &entry.file_name()));
+
+            if entry.file_type()?.is_dir() {
+                self.remove_dir(child.as_ref(), &entry.path(), progress)?;
+            } else {
+                fs::remove_file(entry.path())?;
+                let freed = child.map(|child| self.detach(&child)).unwrap_or_default();
+                progress.add(1, freed);
+            }
+        }
+
+        fs::remove_dir(path)?;
+        let freed = node.map(|node| self.detach(node)).unwrap_or_default();
+        progress.add(0, freed);
+        Ok(())
+    }
+
+    /// Remove the node from the tree and subtract its size from its
+    /// ancestors. Returns the size of the node.
+    fn detach(&self, node: &Arc<RwLock<Node<EntryNode>>>) -> EntrySize {
+        DiskoTree::detach_node(&self.tree, node)
+    }
+
+    /// Make the tree truthful again after the deletion of the entry failed.
+    fn recover(
+        &self,
+        node: &Arc<RwLock<Node<EntryNode>>>,
+        parent: &Arc<RwLock<Node<EntryNode>>>,
+        data: &EntryNode,
+    ) {
+        let is_attached = node
+            .read()
+            .expect("Failed to read node while deleting children.")
+            .get_parent()
+            .is_some();
+        let still_exists = data.path.symlink_metadata().is_ok();
+        // A directory may have lost some of its contents before the deletion
+        // failed, rescan what is left of it.
+        let partially_deleted = matches!(
+            (self.deletion_mode, data.entry_type),
+            (DeletionMode::Permanent, EntryType::Directory)
+        );
+        if is_attached && (partially_deleted || !still_exists) {
+            self.detach(node);
+        }
+        if !(partially_deleted && still_exists) {
+            return;
+        }
+        // What is left of the directory has been detached, the hard links
+        // counted elsewhere in the tree stay counted there.
+        let Some(subtree) = DiskoTree::scan_subtree(
+            &data.path,
+            self.traversal_threads,
+            self.traversal_options.clone(),
+            &self.traversal_errors,
+            DiskoTree::counted_hard_links(&self.tree),
+        ) else {
+            return;
+        };
+        let (size, descendants_count) = {
+            let subtree = subtree.read().expect("Failed to read rescanned subtree");
+            (subtree.data.sizes, subtree.data.descendants_count)
+        };
+        Tree::attach_subtree(parent, subtree)
+            .expect("A freshly scanned subtree should have no parent");
+        DiskoTree::backprop_size(parent, size, descendants_count + 1, BackpropOperation::Add);
+    }
+}
+
+/// Accumulates the progress and sends it to the UI every now and then.
+struct Progress<'a> {
+    report: &'a mut DeletionProgress,
+    sender: &'a mpsc::Sender<DiskoEvent>,
+    last_sent: &'a mut Instant,
+}
+
+impl Progress<'_> {
+    fn add(&mut self, files_removed: usize, freed: EntrySize) {
+        self.report.files_removed += files_removed;
+        self.report.freed += freed;
+        if self.last_sent.elapsed() >= PROGRESS_INTERVAL {
+            let _ = self.sender.send(DiskoEvent::DeletionProgress(*self.report));
+            *self.last_sent = Instant::now();
+        }
+    }
+}
+
+fn count_files(node: &Arc<RwLock<Node<EntryNode>>>) -> usize {
+    let node = node
+        .read()
+        .expect("Failed to read node while deleting children.");
+    match node.data.entry_type {
+        EntryType::File => 1,
+        EntryType::Directory => node.get_children().iter().map(count_files).sum(),
+    }
+}
@@ -0,0 +1,373 @@
+//! Comparing two scans of the same directory.
+//!
+//! The scans are merged into a single tree with the entries of both, every
+//! entry annotated with its sizes in each scan.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use ref_tree::{Node, Tree};
+
+use super::{
+    entry_node::{EntryNode, EntryType},
+    entry_size::{EntrySize, SizeChange},
+};
+
+type EntryRef = Arc<RwLock<Node<EntryNode>>>;
+
+/// Merge the trees of the old and the new scan. Entries are matched by their
+/// paths relative to the roots and carry the sizes of the new scan, the
+/// entries missing from it count as empty.
+pub(super) fn merge(old_root: &EntryRef, new_root: &EntryRef) -> Tree<EntryNode> {
+    let mut tree = Tree::new();
+    let root = tree
+        .create_node_and_set_root(merged_entry(Some(old_root), Some(new_root), None))
+        .expect("The merged tree should not have a root yet");
+
+    let mut stack = vec![(root, Some(old_root.clone()), Some(new_root.clone()))];
+    while let Some((merged, old, new)) = stack.pop() {
+        let mut old_children: HashMap<String, EntryRef> = children(old.as_ref())
+            .into_iter()
+            .map(|child| (name(&child), child))
+            .collect();
+        let new_children = children(new.as_ref());
+        let mut pairs = Vec::new();
+        for new_child in new_children {
+            match old_children.remove(&name(&new_child)) {
+                // An entry which has changed its type is compared as if it
+                // had been removed and added again.
+                Some(old_child) if is_directory(&old_child) != is_directory(&new_child) => {
+                    pairs.push((Some(old_child), None));
+                    pairs.push((None, Some(new_child)));
+                }
+                old_child => pairs.push((old_child, Some(new_child))),
+            }
+        }
+        pairs.extend(old_children.into_values().map(|child| (Some(child), None)));
+
+        for (old, new) in pairs {
+            let child = attach(&merged, old.as_ref(), new.as_ref());
+            if old.as_ref().or(new.as_ref()).is_some_and(is_directory) {
+                stack.push((child, old, new));
+            }
+        }
+    }
+    tree
+}
+
+fn attach(parent: &EntryRef, old: Option<&EntryRef>, new: Option<&EntryRef>) -> EntryRef {
+    Tree::attach_child(parent, merged_entry(old, new, Some(parent)))
+}
+
+/// Create the merged entry from the entry of either scan.
+fn merged_entry(
+    old: Option<&EntryRef>,
+    new: Option<&EntryRef>,
+    parent: Option<&EntryRef>,
+) -> EntryNode {
+    let read = |node: &EntryRef| {
+        node.read()
+            .expect("Failed to read node while comparing scans")
+            .data
+            .clone()
+    };
+    let old = old.map(read);
+    let new = new.map(read);
+    let change = SizeChange {
+        old: old.as_ref().map(|entry| entry.sizes),
+        new: new.as_ref().map(|entry| entry.sizes),
+    };
+
+    let mut entry = match (new, old) {
+        (Some(new), _) => new,
+        // Removed entries take no space in the new scan.
+        (None, Some(old)) => EntryNode {
+            sizes: EntrySize::default(),
+            dir_size: old.dir_size.map(|_| EntrySize::default()),
+            ..old
+        },
+        (None, None) => unreachable!("A merged entry comes from at least one scan"),
+    };
+    // The entries of the old scan may be under another root.
+    if let Some(parent) = parent {
+        entry.path = parent
+            .read()
+            .expect("Failed to read parent while comparing scans")
+            .data
+            .path
+            .join(&entry.name);
+    }
+    entry.change = Some(change);
+    entry
+}
+
+fn children(node: Option<&EntryRef>) -> Vec<EntryRef> {
+    node.map(|node| {
+        node.read()
+            .expect("Failed to read node while comparing scans")
+            .get_children()
+    })
+    .unwrap_or_default()
+}
+
+fn name(node: &EntryRef) -> String {
+    node.read()
+        .expect("Failed to read node while comparing scans")
+        .data
+        .name
+        .clone()
+}
+
+fn is_directory(node: &EntryRef) -> bool {
+    matches!(
+        node.read()
+            .expect("Failed to read node while comparing scans")
+            .data
+            .entry_type,
+        EntryType::Directory
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::*;
+    use crate::backend::{
+        disko_tree::DiskoTree,
+        entry_size::{ChangeKind, SizeMetric},
+    };
+
+    /// Builds a scanned tree with the sizes of the directories summed.
+    struct Scan {
+        tree: Tree<EntryNode>,
+    }
+
+    impl Scan {
+        fn new(root: &str) -> Self {
+            let mut tree = Tree::new();
+            tree.create_node_and_set_root(EntryNode::for_test(root, EntryType::Directory, 0))
+                .unwrap();
+            Self { tree }
+        }
+
+        fn root(&self) -> EntryRef {
+            self.tree.get_root().unwrap()
+        }
+
+        /// Add the entry under the directory with the given path relative
+        /// to the root.
+        fn add(&self, parent: &str, name: &str, entry_type: EntryType, size: u64) -> &Self {
+            let mut parent_node = self.root();
+            for component in parent.split('/').filter(|component| !component.is_empty()) {
+                let child = parent_node
+                    .read()
+                    .unwrap()
+                    .get_children()
+                    .into_iter()
+                    .find(|child| child.read().unwrap().data.name == component)
+                    .unwrap();
+                parent_node = child;
+            }
+            let path = parent_node.read().unwrap().data.path.join(name);
+            let entry = EntryNode::for_test(path.to_str().unwrap(), entry_type, size);
+            DiskoTree::attach_for_test(&parent_node, entry);
+            self
+        }
+
+        fn file(&self, parent: &str, name: &str, size: u64) -> &Self {
+            self.add(parent, name, EntryType::File, size)
+        }
+
+        fn directory(&self, parent: &str, name: &str) -> &Self {
+            self.add(parent, name, EntryType::Directory, 0)
+        }
+    }
+
+    /// All entries of the merged tree.
+    fn entries(tree: &Tree<EntryNode>) -> Vec<EntryNode> {
+        let mut entries = Vec::new();
+        let mut stack = vec![tree.get_root().unwrap()];
+        while let Some(node) = stack.pop() {
+            let node = node.read().unwrap();
+            stack.extend(node.get_children());
+            entries.push(node.data.clone());
+        }
+        entries
+    }
+
+    /// The kind of change and the growth of the entries with the path.
+    fn changes(tree: &Tree<EntryNode>, path: &str) -> Vec<(ChangeKind, i128)> {
+        let mut changes: Vec<_> = entries(tree)
+            .into_iter()
+            .filter(|entry| entry.path == Path::new(path))
+            .map(|entry| {
+                let change = entry.change.unwrap();
+                (
+                    change.kind(SizeMetric::Apparent),
+                    change.delta(SizeMetric::Apparent),
+                )
+            })
+            .collect();
+        changes.sort_by_key(|(_, delta)| *delta);
+        changes
+    }
+
+    #[test]
+    fn test_merge_files() {
+        let old = Scan::new("/scan");
+        old.file("", "same", 10)
+            .file("", "grown", 10)
+            .file("", "shrunk", 10)
+            .file("", "removed", 5);
+        let new = Scan::new("/scan");
+        new.file("", "same", 10)
+            .file("", "grown", 15)
+            .file("", "shrunk", 3)
+            .file("", "added", 7);
+        let merged = merge(&old.root(), &new.root());
+
+        assert_eq!(entries(&merged).len(), 6);
+        assert_eq!(changes(&merged, "/scan"), vec![(ChangeKind::Unchanged, 0)]);
+        assert_eq!(
+            changes(&merged, "/scan/same"),
+            vec![(ChangeKind::Unchanged, 0)]
+        );
+        assert_eq!(
+            changes(&merged, "/scan/grown"),
+            vec![(ChangeKind::Grown, 5)]
+        );
+        assert_eq!(
+            changes(&merged, "/scan/shrunk"),
+            vec![(ChangeKind::Shrunk, -7)]
+        );
+        assert_eq!(
+            changes(&merged, "/scan/removed"),
+            vec![(ChangeKind::Removed, -5)]
+        );
+        assert_eq!(
+            changes(&merged, "/scan/added"),
+            vec![(ChangeKind::Added, 7)]
+        );
+    }
+
+    #[test]
+    fn test_merge_removed_directory() {
+        let old = Scan::new("/scan");
+        old.directory("", "gone").file("gone", "file", 10);
+        let new = Scan::new("/scan");
+        let merged = merge(&old.root(), &new.root());
+
+        assert_eq!(changes(&merged, "/scan"), vec![(ChangeKind::Shrunk, -10)]);
+        assert_eq!(
+            changes(&merged, "/scan/gone"),
+            vec![(ChangeKind::Removed, -10)]
+        );
+        assert_eq!(
+            changes(&merged, "/scan/gone/file"),
+            vec![(ChangeKind::Removed, -10)]
+        );
+        // Removed entries take no space in the merged tree.
+        for entry in entries(&merged) {
+            assert_eq!(entry.sizes.apparent_size, 0, "{:?}", entry.path);
+        }
+        let gone = entries(&merged)
+            .into_iter()
+            .find(|entry| entry.name == "gone")
+            .unwrap();
+        assert_eq!(gone.dir_size.unwrap().apparent_size, 0);
+    }
+
+    #[test]
+    fn test_merge_added_directory() {
+        let old = Scan::new("/scan");
+        let new = Scan::new("/scan");
+        new.directory("", "new").file("new", "file", 10);
+        let merged = merge(&old.root(), &new.root());
+
+        assert_eq!(changes(&merged, "/scan/new"), vec![(ChangeKind::Added, 10)]);
+        assert_eq!(
+            changes(&merged, "/scan/new/file"),
+            vec![(ChangeKind::Added, 10)]
+        );
+    }
+
+    #[test]
+    fn test_merge_type_change() {
+        let old = Scan::new("/scan");
+        old.file("", "entry", 10);
+        let new = Scan::new("/scan");
+        new.directory("", "entry").file("entry", "file", 25);
+        let merged = merge(&old.root(), &new.root());
+
+        // The file has been removed and the directory added.
+        assert_eq!(
+            changes(&merged, "/scan/entry"),
+            vec![(ChangeKind::Removed, -10), (ChangeKind::Added, 25)]
+        );
+        assert_eq!(
+            changes(&merged, "/scan/entry/file"),
+            vec![(ChangeKind::Added, 25)]
+        );
+        let types: Vec<_> = entries(&merged)
+            .into_iter()
+            .filter(|entry| entry.name == "entry")
+            .map(|entry| {
+                (
+                    entry.change.unwrap().kind(SizeMetric::Apparent),
+                    entry.entry_type,
+                )
+            })
+            .collect();
+        assert_eq!(types.len(), 2);
+        for (kind, entry_type) in types {
+            assert_eq!(
+                matches!(entry_type, EntryType::Directory),
+                kind == ChangeKind::Added
+            );
+        }
+    }
+
+    #[test]
+    fn test_merge_directory_to_file() {
+        let old = Scan::new("/scan");
+        old.directory("", "entry").file("entry", "file", 25);
+        let new = Scan::new("/scan");
+        new.file("", "entry", 10);
+        let merged = merge(&old.root(), &new.root());
+
+        assert_eq!(
+            changes(&merged, "/scan/entry"),
+            vec![(ChangeKind::Removed, -25), (ChangeKind::Added, 10)]
+        );
+        // The children of the removed directory are kept.
+        assert_eq!(
+            changes(&merged, "/scan/entry/file"),
+            vec![(ChangeKind::Removed, -25)]
+        );
+    }
+
+    #[test]
+    fn test_merge_paths_under_new_root() {
+        let old = Scan::new("/old");
+        old.directory("", "directory").file("directory", "file", 10);
+        let new = Scan::new("/new");
+        let merged = merge(&old.root(), &new.root());
+
+        let mut paths: Vec<_> = entries(&merged)
+            .into_iter()
+            .map(|entry| entry.path)
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/new"),
+                PathBuf::from("/new/directory"),
+                PathBuf::from("/new/directory/file"),
+            ]
+        );
+    }
+}
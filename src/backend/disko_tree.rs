@@ -1,14 +1,19 @@
 use std::{
+    collections::HashSet,
+    ffi::OsStr,
     fmt,
+    fs::{self, File},
+    io::{self, BufReader, Write},
     path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicBool, Ordering},
-        mpsc, Arc, RwLock,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc, Mutex, RwLock,
     },
     thread,
+    time::SystemTime,
 };
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use jwalk::{
     DirEntry,
     Parallelism::{RayonNewPool, Serial},
@@ -18,11 +23,27 @@ use jwalk::{
 use crate::ui::event_handling::DiskoEvent;
 
 use super::{
-    entry_node::{EntryNode, EntryNodeView},
-    entry_size::EntrySize,
-    tree_walk_state::{CustomJWalkClientState, TreeWalkAncestor, TreeWalkState},
+    deletion::{DeletedEntry, DeletionBatch, DeletionReport, DeletionWorker, RestoreReport},
+    diff,
+    duplicates::{self, DuplicateGroup},
+    entry_node::{EntryNode, EntryNodeView, EntryType},
+    entry_size::{EntrySize, SizeMetric},
+    export::{self, ExportFormat},
+    largest_files::{LargestFiles, SharedLargestFiles},
+    ncdu,
+    search::SearchQuery,
+    snapshot::{self, SnapshotInfo},
+    sort_mode::{SortKey, SortMode},
+    trash,
+    traversal_options::TraversalOptions,
+    tree_walk_state::{
+        CustomJWalkClientState, TraversalError, TraversalErrors, TreeWalkAncestor, TreeWalkState,
+    },
 };
 
+#[cfg(target_os = "linux")]
+use super::watcher::Watcher;
+
 use ref_tree::{Node, Tree};
 
 pub enum BackpropOperation {
@@ -30,39 +51,224 @@ pub enum BackpropOperation {
     Subtract,
 }
 
+/// How the entries are deleted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeletionMode {
+    /// Move the entries to the trash, so they can be restored.
+    #[default]
+    Trash,
+    /// Remove the entries for good.
+    Permanent,
+}
+
+/// Where the data of the tree come from.
+#[derive(Clone, Debug, Default)]
+pub enum TreeSource {
+    /// The tree is built by traversing the file system.
+    #[default]
+    FileSystem,
+    /// The tree is read from an ncdu export file.
+    NcduImport(PathBuf),
+    /// The tree is loaded from a snapshot of an earlier scan.
+    Snapshot(SnapshotInfo),
+    /// The tree merges two scans to show how the entries have changed.
+    Diff {
+        old: Box<TreeSource>,
+        new: Box<TreeSource>,
+    },
+}
+
 #[derive(Default)]
 pub struct DiskoTree {
     tree: Arc<RwLock<Tree<EntryNode>>>,
     current_directory: Option<Arc<RwLock<Node<EntryNode>>>>,
     traversal_handler: Option<thread::JoinHandle<()>>,
+    deletion_handler: Option<thread::JoinHandle<DeletionReport>>,
+    duplicates_handler: Option<thread::JoinHandle<Option<Vec<DuplicateGroup>>>>,
     root: PathBuf,
     traversal_threads: usize,
+    traversal_options: Arc<TraversalOptions>,
+    excluded_count: Arc<AtomicUsize>,
+    traversal_errors: TraversalErrors,
+    largest_files: SharedLargestFiles,
     is_traversing: Arc<AtomicBool>,
     stop_traversing: Arc<AtomicBool>,
+    stop_deleting: Arc<AtomicBool>,
+    stop_finding_duplicates: Arc<AtomicBool>,
+    #[cfg(target_os = "linux")]
+    watcher: Option<Watcher>,
+    source: TreeSource,
 }
 
 // Public interface
 
 impl DiskoTree {
-    pub(crate) fn new(root: PathBuf, traversal_threads: usize) -> Self {
+    pub(crate) fn new(
+        root: PathBuf,
+        traversal_threads: usize,
+        traversal_options: TraversalOptions,
+    ) -> Self {
         Self {
             tree: Arc::new(RwLock::new(Tree::new())),
             current_directory: None,
             traversal_handler: None,
+            deletion_handler: None,
+            duplicates_handler: None,
             root,
             traversal_threads,
+            traversal_options: Arc::new(traversal_options),
+            excluded_count: Arc::new(AtomicUsize::new(0)),
+            traversal_errors: TraversalErrors::default(),
+            largest_files: SharedLargestFiles::default(),
             is_traversing: Arc::new(AtomicBool::new(false)),
             stop_traversing: Arc::new(AtomicBool::new(false)),
+            stop_deleting: Arc::new(AtomicBool::new(false)),
+            stop_finding_duplicates: Arc::new(AtomicBool::new(false)),
+            #[cfg(target_os = "linux")]
+            watcher: None,
+            source: TreeSource::FileSystem,
         }
     }
 
+    /// Create the tree from an ncdu export file instead of traversing the
+    /// file system.
+    pub(crate) fn from_ncdu_export(path: PathBuf) -> Result<Self> {
+        let file =
+            File::open(&path).with_context(|| format!("Failed to open '{}'", path.display()))?;
+        let (tree, excluded_count) = ncdu::import(BufReader::new(file))
+            .with_context(|| format!("Failed to import '{}'", path.display()))?;
+        let root = tree
+            .get_root()
+            .context("The imported tree is empty")?
+            .read()
+            .expect("Failed to read root of imported tree")
+            .data
+            .path
+            .clone();
+
+        Ok(Self {
+            tree: Arc::new(RwLock::new(tree)),
+            root,
+            excluded_count: Arc::new(AtomicUsize::new(excluded_count)),
+            source: TreeSource::NcduImport(path),
+            ..Self::default()
+        })
+    }
+
+    /// Load the tree from a snapshot file instead of traversing the file
+    /// system.
+    pub(crate) fn from_snapshot(path: PathBuf) -> Result<Self> {
+        let file =
+            File::open(&path).with_context(|| format!("Failed to open '{}'", path.display()))?;
+        let length = file.metadata()?.len();
+        let contents = snapshot::load(path.clone(), BufReader::new(file), length)
+            .with_context(|| format!("Failed to load snapshot '{}'", path.display()))?;
+        let root = contents
+            .tree
+            .get_root()
+            .context("The snapshot is empty")?
+            .read()
+            .expect("Failed to read root of loaded tree")
+            .data
+            .path
+            .clone();
+
+        Ok(Self {
+            tree: Arc::new(RwLock::new(contents.tree)),
+            root,
+            excluded_count: Arc::new(AtomicUsize::new(contents.excluded_count)),
+            traversal_errors: Arc::new(Mutex::new(contents.errors)),
+            source: TreeSource::Snapshot(contents.info),
+            ..Self::default()
+        })
+    }
+
+    /// Merge the trees of two scans, every entry showing how it has changed
+    /// from the old scan to the new one.
+    pub(crate) fn from_diff(old: DiskoTree, new: DiskoTree) -> Result<Self> {
+        // Scans of different directories would only show everything as
+        // removed and added.
+        if old.root != new.root {
+            bail!(
+                "Cannot compare scans of different directories, '{}' and '{}'",
+                old.root.display(),
+                new.root.display()
+            );
+        }
+        let old_root = old
+            .tree
+            .read()
+            .expect("Failed to read the old tree")
+            .get_root()
+            .context("The old scan is empty")?;
+        let new_root = new
+            .tree
+            .read()
+            .expect("Failed to read the new tree")
+            .get_root()
+            .context("The new scan is empty")?;
+        let tree = diff::merge(&old_root, &new_root);
+
+        Ok(Self {
+            tree: Arc::new(RwLock::new(tree)),
+            root: new.root,
+            excluded_count: new.excluded_count,
+            traversal_errors: new.traversal_errors,
+            source: TreeSource::Diff {
+                old: Box::new(old.source),
+                new: Box::new(new.source),
+            },
+            ..Self::default()
+        })
+    }
+
     pub fn root_path(&self) -> PathBuf {
         self.root.clone()
     }
 
+    pub(crate) fn source(&self) -> &TreeSource {
+        &self.source
+    }
+
+    /// Whether the tree does not reflect the file system, so its entries
+    /// must not be deleted.
+    pub(crate) fn is_read_only(&self) -> bool {
+        !matches!(self.source, TreeSource::FileSystem)
+    }
+
+    /// Whether the tree compares two scans.
+    pub(crate) fn is_diff(&self) -> bool {
+        matches!(self.source, TreeSource::Diff { .. })
+    }
+
+    /// The number of entries skipped because they matched an exclude pattern.
+    pub(crate) fn excluded_count(&self) -> usize {
+        self.excluded_count.load(Ordering::Relaxed)
+    }
+
+    /// The entries which could not be read during the traversal, sorted by
+    /// path.
+    pub(crate) fn traversal_errors(&self) -> Vec<TraversalError> {
+        let mut errors = self
+            .traversal_errors
+            .lock()
+            .expect("Failed to lock traversal errors")
+            .clone();
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        errors
+    }
+
+    pub(crate) fn traversal_error_count(&self) -> usize {
+        self.traversal_errors
+            .lock()
+            .expect("Failed to lock traversal errors")
+            .len()
+    }
+
     fn get_children(
         node: &std::sync::RwLockReadGuard<'_, Node<EntryNode>>,
-        sort_by_disk_size: bool,
+        sort_mode: SortMode,
+        size_metric: SizeMetric,
     ) -> Vec<EntryNodeView> {
         let mut children: Vec<EntryNodeView> = node
             .get_children()
@@ -78,13 +284,7 @@ impl DiskoTree {
             })
             .collect();
 
-        children.sort_by(|a, b| {
-            if sort_by_disk_size {
-                b.sizes.disk_size.cmp(&a.sizes.disk_size)
-            } else {
-                b.sizes.apparent_size.cmp(&a.sizes.apparent_size)
-            }
-        });
+        children.sort_by(|a, b| sort_mode.compare(a, b, size_metric));
 
         children
     }
@@ -128,12 +328,148 @@ impl DiskoTree {
         Ok(())
     }
 
+    /// Switch the current working directory to the directory at the given
+    /// path.
+    pub(crate) fn switch_to_directory(&mut self, path: &Path) -> Result<()> {
+        let directory = Self::find_node(&self.tree, path)
+            .with_context(|| format!("'{}' is not in the tree", path.display()))?;
+        if let EntryType::File = directory
+            .read()
+            .expect("Failed to read node")
+            .data
+            .entry_type
+        {
+            bail!("'{}' is not a directory", path.display());
+        }
+        self.current_directory = Some(directory);
+        Ok(())
+    }
+
+    /// Find all entries in the tree, except the root, matching the query.
+    /// The entries are sorted by size, the largest first.
+    pub(crate) fn search(
+        &self,
+        query: &SearchQuery,
+        size_metric: SizeMetric,
+    ) -> Vec<EntryNodeView> {
+        let Some(root) = self.tree.read().expect("Failed to read tree").get_root() else {
+            return Vec::new();
+        };
+
+        let mut matches = Vec::new();
+        let mut stack = root.read().expect("Failed to read root").get_children();
+        while let Some(node) = stack.pop() {
+            let node = node.read().expect("Failed to read node while searching");
+            if query.matches(&node.data, size_metric) {
+                matches.push(EntryNodeView::from_entry_node(&node.data));
+            }
+            stack.extend(node.get_children());
+        }
+
+        matches.sort_by(|a, b| SortMode::new(SortKey::Size).compare(a, b, size_metric));
+        matches
+    }
+
+    /// Set how many of the largest files are kept track of.
+    pub(crate) fn set_largest_files_count(&mut self, count: usize) {
+        self.largest_files = Arc::new(Mutex::new(LargestFiles::new(count)));
+    }
+
+    /// Get the view of the root and the largest files of the whole tree,
+    /// named by their paths relative to the root.
+    /// Returns `None` if the traversal has not yet computed a root.
+    pub(crate) fn get_largest_files_view(
+        &self,
+        sort_mode: SortMode,
+        size_metric: SizeMetric,
+    ) -> Option<(EntryNodeView, Vec<EntryNodeView>)> {
+        let root = self.tree.read().expect("Failed to read tree").get_root()?;
+        let root_view =
+            EntryNodeView::from_entry_node(&root.read().expect("Failed to read root").data);
+        let mut largest_files = self
+            .largest_files
+            .lock()
+            .expect("Failed to lock largest files");
+        // The files seen by the traversal are kept up to date while it runs,
+        // otherwise they are collected again from the tree if they are
+        // outdated or have never been collected, e.g., for imported trees.
+        if !self.is_traversing() && (largest_files.is_stale() || largest_files.is_empty()) {
+            largest_files.clear();
+            let mut stack = vec![root];
+            while let Some(node) = stack.pop() {
+                let node = node.read().expect("Failed to read node");
+                if let EntryType::File = node.data.entry_type {
+                    largest_files.offer(&node.data.path, node.data.sizes);
+                }
+                stack.extend(node.get_children());
+            }
+        }
+
+        let mut files: Vec<EntryNodeView> = largest_files
+            .paths(size_metric)
+            .into_iter()
+            .filter_map(|path| Self::find_node(&self.tree, &path))
+            .filter_map(|node| {
+                let node = node.read().expect("Failed to read node");
+                if !matches!(node.data.entry_type, EntryType::File) {
+                    return None;
+                }
+                let mut entry = EntryNodeView::from_entry_node(&node.data);
+                if let Ok(relative) = entry.path.strip_prefix(&self.root) {
+                    entry.name = relative.to_string_lossy().into_owned();
+                }
+                Some(entry)
+            })
+            .collect();
+        files.sort_by(|a, b| sort_mode.compare(a, b, size_metric));
+        Some((root_view, files))
+    }
+
+    /// Get the view of the root and the files of the groups of duplicates,
+    /// named by their paths relative to the root. Files which are no longer
+    /// in the tree are dropped from the groups, as are the groups left with a
+    /// single file.
+    /// Returns `None` if the traversal has not yet computed a root.
+    pub(crate) fn get_duplicates_view(
+        &self,
+        groups: &mut Vec<DuplicateGroup>,
+    ) -> Option<(EntryNodeView, Vec<EntryNodeView>)> {
+        let root = self.tree.read().expect("Failed to read tree").get_root()?;
+        let root_view =
+            EntryNodeView::from_entry_node(&root.read().expect("Failed to read root").data);
+
+        let mut files = Vec::new();
+        groups.retain_mut(|group| {
+            let group_files: Vec<EntryNodeView> = group
+                .paths
+                .iter()
+                .filter_map(|path| Self::find_node(&self.tree, path))
+                .map(|node| {
+                    let node = node.read().expect("Failed to read node");
+                    let mut entry = EntryNodeView::from_entry_node(&node.data);
+                    if let Ok(relative) = entry.path.strip_prefix(&self.root) {
+                        entry.name = relative.to_string_lossy().into_owned();
+                    }
+                    entry
+                })
+                .collect();
+            if group_files.len() < 2 {
+                return false;
+            }
+            group.paths = group_files.iter().map(|entry| entry.path.clone()).collect();
+            files.extend(group_files);
+            true
+        });
+        Some((root_view, files))
+    }
+
     /// Get the view of the current directory and its children.
     /// Returns `None` if the current directory is not set, i.e., the traversal
     /// has not yet computed a root.
     pub(crate) fn get_current_dir_view(
         &mut self,
-        sort_by_disk_size: bool,
+        sort_mode: SortMode,
+        size_metric: SizeMetric,
     ) -> Option<(EntryNodeView, Vec<EntryNodeView>)> {
         if self.current_directory.is_none() {
             self.current_directory = self
@@ -142,12 +478,13 @@ impl DiskoTree {
                 .expect("Failed to read the underlying tree in diskotree")
                 .get_root();
         }
+        self.follow_rescanned_directory();
         let current_directory = self
             .current_directory
             .as_ref()?
             .read()
             .expect("Failed to read current directory");
-        let children = Self::get_children(&current_directory, sort_by_disk_size);
+        let children = Self::get_children(&current_directory, sort_mode, size_metric);
         let current_directory_view = EntryNodeView::from_entry_node(&current_directory.data);
         Some((current_directory_view, children))
     }
@@ -159,7 +496,8 @@ impl DiskoTree {
     pub(crate) fn get_subdir_of_current_dir_view(
         &self,
         index: usize,
-        sort_by_disk_size: bool,
+        sort_mode: SortMode,
+        size_metric: SizeMetric,
     ) -> Option<Vec<EntryNodeView>> {
         let subdir_arc = {
             let current_directory = self
@@ -173,14 +511,31 @@ impl DiskoTree {
             .read()
             .expect("Failed to read subdir while getting subdir view");
 
-        Some(Self::get_children(&subdir, sort_by_disk_size))
+        Some(Self::get_children(&subdir, sort_mode, size_metric))
     }
 
+    /// Walk the directory at the root path, attaching it to the ancestor,
+    /// i.e., as the root of the tree or as a child of a node.
+    #[allow(clippy::too_many_arguments)]
     fn jwalk_walk_dir(
         root: PathBuf,
-        tree: Arc<RwLock<Tree<EntryNode>>>,
+        ancestor: TreeWalkAncestor,
         traversal_threads: usize,
+        traversal_options: Arc<TraversalOptions>,
+        excluded_count: Arc<AtomicUsize>,
+        errors: TraversalErrors,
+        largest_files: SharedLargestFiles,
+        counted_hard_links: HashSet<u64>,
     ) -> WalkDirGeneric<(TreeWalkState, ())> {
+        let state = TreeWalkState::new(
+            ancestor,
+            &root,
+            traversal_options,
+            excluded_count,
+            errors,
+            largest_files,
+            counted_hard_links,
+        );
         WalkDirGeneric::<(TreeWalkState, ())>::new(root)
             .sort(true)
             .parallelism(if traversal_threads == 1 {
@@ -189,7 +544,7 @@ impl DiskoTree {
                 RayonNewPool(traversal_threads)
             })
             .skip_hidden(false)
-            .root_read_dir_state(TreeWalkState::new(tree))
+            .root_read_dir_state(state)
             .process_read_dir(|depth, dir_path, state, children| {
                 Self::process_dir(depth, dir_path, state, children);
             })
@@ -200,24 +555,96 @@ impl DiskoTree {
     /// are calculated.
     /// This method is non-blocking.
     pub(crate) fn start_background_traversal(&mut self, sender: mpsc::Sender<DiskoEvent>) {
-        let tree = self.tree.clone();
-        let is_traversing = self.is_traversing.clone();
-        let stop_traversing = self.stop_traversing.clone();
-        let root = self.root.clone();
-        let traversal_threads = self.traversal_threads;
-        self.traversal_handler = Some(thread::spawn(move || {
-            is_traversing.store(true, Ordering::Release);
+        if self.is_read_only() {
+            // There is nothing to traverse.
+            let _ = sender.send(DiskoEvent::TraversalFinished);
+            return;
+        }
+        self.spawn_traversal(
+            self.root.clone(),
+            TreeWalkAncestor::Tree(self.tree.clone()),
+            self.excluded_count.clone(),
+            self.largest_files.clone(),
+            HashSet::new(),
+            sender,
+        );
+    }
 
-            for _ in Self::jwalk_walk_dir(root, tree, traversal_threads) {
-                if stop_traversing.load(Ordering::Relaxed) {
-                    break;
-                }
-            }
+    /// Drop the subtree of the directory at the given path and scan it again
+    /// on a separate thread, the rescanned entries are attached as they are
+    /// found. Rescanning the root starts the whole traversal from scratch.
+    /// This method is non-blocking.
+    pub(crate) fn start_background_rescan(
+        &mut self,
+        path: &Path,
+        sender: mpsc::Sender<DiskoEvent>,
+    ) -> Result<()> {
+        if self.is_read_only() {
+            bail!("Imported trees cannot be rescanned");
+        }
+        if self.is_traversing() {
+            bail!("The traversal is still running");
+        }
+        if self.deletion_handler.is_some() {
+            bail!("Cannot rescan while deleting");
+        }
+        let node = Self::find_node(&self.tree, path)
+            .with_context(|| format!("'{}' is not in the tree", path.display()))?;
+        let (parent, is_directory, mount_point) = {
+            let node = node.read().expect("Failed to read node");
+            (
+                node.get_parent().and_then(|parent| parent.upgrade()),
+                matches!(node.data.entry_type, EntryType::Directory),
+                node.data.mount_point,
+            )
+        };
+        if !is_directory {
+            bail!("'{}' is not a directory", path.display());
+        }
+        if mount_point {
+            bail!("'{}' is on another file system", path.display());
+        }
+        if let Some(handler) = self.traversal_handler.take() {
+            handler.join().expect("Failed to join traversal thread.");
+        }
+        self.traversal_errors
+            .lock()
+            .expect("Failed to lock traversal errors")
+            .retain(|error| !error.path.starts_with(path));
+        self.invalidate_largest_files();
 
-            is_traversing.store(false, Ordering::Release);
-            // Here we just ignore if the event handler has stopped.
-            let _ = sender.send(DiskoEvent::TraversalFinished);
-        }));
+        let Some(parent) = parent else {
+            *self.tree.write().expect("Failed to write to tree") = Tree::new();
+            self.current_directory = None;
+            self.excluded_count.store(0, Ordering::Relaxed);
+            self.largest_files
+                .lock()
+                .expect("Failed to lock largest files")
+                .clear();
+            self.start_background_traversal(sender);
+            return Ok(());
+        };
+
+        Self::detach_node(&self.tree, &node);
+        if !path.is_dir() {
+            self.leave_removed_directory();
+            bail!("'{}' no longer exists", path.display());
+        }
+        // The directory itself is counted here, its contents once they are
+        // processed.
+        Self::backprop_size(&parent, EntrySize::default(), 1, BackpropOperation::Add);
+        let counted_hard_links = Self::counted_hard_links(&self.tree);
+        self.spawn_traversal(
+            path.to_path_buf(),
+            TreeWalkAncestor::Parent(parent),
+            // The excluded entries have already been counted by the traversal.
+            Arc::default(),
+            // The largest files are collected again once they are needed.
+            Arc::new(Mutex::new(LargestFiles::new(0))),
+            counted_hard_links,
+            sender,
+        );
+        Ok(())
     }
 
     /// Check if the traversal thread is still running.
@@ -235,12 +662,72 @@ impl DiskoTree {
     }
 
     pub(crate) fn traverse(&mut self) {
-        for _ in Self::jwalk_walk_dir(self.root.clone(), self.tree.clone(), self.traversal_threads)
-        {
+        if self.is_read_only() {
+            return;
         }
+        let walk = Self::jwalk_walk_dir(
+            self.root.clone(),
+            TreeWalkAncestor::Tree(self.tree.clone()),
+            self.traversal_threads,
+            self.traversal_options.clone(),
+            self.excluded_count.clone(),
+            self.traversal_errors.clone(),
+            self.largest_files.clone(),
+            HashSet::new(),
+        );
+        Self::run_walk(walk, &self.tree, &self.traversal_errors, None);
     }
 
-    pub(crate) fn delete_entries(&self, mut indices: Vec<usize>) -> Result<()> {
+    /// Write the whole tree in the given format.
+    pub(crate) fn export(&self, format: ExportFormat, writer: impl Write) -> Result<()> {
+        let Some(root) = self
+            .tree
+            .read()
+            .expect("Failed to read the underlying tree in diskotree")
+            .get_root()
+        else {
+            bail!("Nothing to export, the tree is empty");
+        };
+        match format {
+            ExportFormat::Json => export::export_json(&root, writer),
+            ExportFormat::Ncdu => ncdu::export(&root, writer),
+        }
+    }
+
+    /// Write the whole tree as a snapshot of the scan started at the given
+    /// time.
+    pub(crate) fn save_snapshot(&self, scanned_at: SystemTime, writer: impl Write) -> Result<()> {
+        let Some(root) = self
+            .tree
+            .read()
+            .expect("Failed to read the underlying tree in diskotree")
+            .get_root()
+        else {
+            bail!("Nothing to save, the tree is empty");
+        };
+        snapshot::save(
+            &root,
+            scanned_at,
+            &self.traversal_options,
+            self.excluded_count(),
+            &self.traversal_errors(),
+            writer,
+        )
+    }
+
+    /// Starts deleting the children of the current directory at the given
+    /// indices on a separate thread. The nodes are removed from the tree as
+    /// the entries vanish from the file system.
+    /// This method is non-blocking.
+    pub(crate) fn start_background_deletion(
+        &mut self,
+        mut indices: Vec<usize>,
+        deletion_mode: DeletionMode,
+        sender: mpsc::Sender<DiskoEvent>,
+    ) -> Result<()> {
+        if self.deletion_handler.is_some() {
+            bail!("Another deletion is still running");
+        }
         let current_directory_arc = self
             .current_directory
             .clone()
@@ -249,67 +736,732 @@ impl DiskoTree {
         let children = {
             let current_directory = current_directory_arc
                 .read()
-                .expect("Failed to write current directory");
+                .expect("Failed to read current directory");
 
             current_directory.get_children()
         };
 
-        let mut deleted_size = EntrySize::default();
-
-        // When deleting children, we modify the size of the vector containing children,
-        // hence indices may no longer correspond to desired children. Therefore we sort them in descending order,
-        // so children are deleted from end and hence we omit the neccessity to adjust indices upon child deletion.
         indices.sort_unstable();
-        indices.reverse();
-
-        for child_index in indices {
-            let child = children
-                .get(child_index)
-                .context("Provided index is out of bounds.")?;
-            let child_data = {
-                let child = child.clone();
-                let read_child = child
-                    .read()
-                    .expect("Failed to read child while deleting children.");
+        let children = indices
+            .into_iter()
+            .map(|index| {
+                children
+                    .get(index)
+                    .cloned()
+                    .context("Provided index is out of bounds.")
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-                read_child.data.clone()
-            };
+        self.spawn_deletion(children, deletion_mode, sender);
+        Ok(())
+    }
 
-            child_data.delete_entry()?;
+    /// Starts deleting the entries at the given paths anywhere in the tree on
+    /// a separate thread, like `start_background_deletion`.
+    /// This method is non-blocking.
+    pub(crate) fn start_background_deletion_of_paths(
+        &mut self,
+        paths: &[PathBuf],
+        deletion_mode: DeletionMode,
+        sender: mpsc::Sender<DiskoEvent>,
+    ) -> Result<()> {
+        if self.deletion_handler.is_some() {
+            bail!("Another deletion is still running");
+        }
+        let entries = paths
+            .iter()
+            .map(|path| {
+                Self::find_node(&self.tree, path)
+                    .with_context(|| format!("'{}' is not in the tree", path.display()))
+            })
+            .collect::<Result<Vec<_>>>()?;
 
-            deleted_size += child_data.sizes;
-            self.tree
-                .clone()
-                .write()
-                .expect("Failed to write to tree while deleting children.")
-                .remove_subtree(child)
-                .expect("Failed to delete child.");
+        self.spawn_deletion(entries, deletion_mode, sender);
+        Ok(())
+    }
+
+    /// Asks the deletion thread to stop after the entry it is removing.
+    pub(crate) fn cancel_background_deletion(&self) {
+        self.stop_deleting.store(true, Ordering::Relaxed);
+    }
+
+    /// Waits for the deletion thread to finish and returns its report.
+    /// Returns `None` if no deletion has been started.
+    pub(crate) fn finish_background_deletion(&mut self) -> Option<DeletionReport> {
+        let report = self
+            .deletion_handler
+            .take()
+            .map(|handler| handler.join().expect("Failed to join deletion thread."));
+        if report.is_some() {
+            self.invalidate_largest_files();
         }
+        report
+    }
 
-        Self::backprop_size(
-            &current_directory_arc,
-            deleted_size,
-            BackpropOperation::Subtract,
-        );
+    /// Starts looking for files with the same contents on a separate thread.
+    /// This method is non-blocking.
+    pub(crate) fn start_duplicate_search(
+        &mut self,
+        sender: mpsc::Sender<DiskoEvent>,
+    ) -> Result<()> {
+        if self.duplicates_handler.is_some() {
+            bail!("Already looking for duplicates");
+        }
+        let root = self
+            .tree
+            .read()
+            .expect("Failed to read the underlying tree in diskotree")
+            .get_root()
+            .context("The tree is empty")?;
+        let cancel = self.stop_finding_duplicates.clone();
+        cancel.store(false, Ordering::Relaxed);
+        self.duplicates_handler = Some(thread::spawn(move || {
+            let groups = duplicates::find_duplicates(&root, &cancel);
+            // Here we just ignore if the event handler has stopped.
+            let _ = sender.send(DiskoEvent::DuplicatesFound);
+            groups
+        }));
         Ok(())
     }
+
+    pub(crate) fn is_finding_duplicates(&self) -> bool {
+        self.duplicates_handler.is_some()
+    }
+
+    /// Asks the duplicate search to stop as soon as possible.
+    pub(crate) fn cancel_duplicate_search(&self) {
+        self.stop_finding_duplicates.store(true, Ordering::Relaxed);
+    }
+
+    /// Waits for the duplicate search to finish and returns the groups of
+    /// duplicates. Returns `None` if no search has been started or if it has
+    /// been cancelled.
+    pub(crate) fn finish_duplicate_search(&mut self) -> Option<Vec<DuplicateGroup>> {
+        self.duplicates_handler
+            .take()
+            .and_then(|handler| handler.join().expect("Failed to join duplicates thread."))
+    }
+
+    /// Start watching the scanned directories for changes, which are then
+    /// applied by `apply_watched_changes`.
+    /// Returns the number of watched directories and the number of all
+    /// directories, which is larger once the limit of watches is reached.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn start_watching(
+        &mut self,
+        sender: mpsc::Sender<DiskoEvent>,
+    ) -> Result<(usize, usize)> {
+        let root = self
+            .tree
+            .read()
+            .expect("Failed to read the underlying tree in diskotree")
+            .get_root()
+            .context("The tree is empty")?;
+        let mut watcher = Watcher::new(sender).context("Failed to initialize inotify")?;
+        let counts = Self::watch_subtree(&mut watcher, &root);
+        self.watcher = Some(watcher);
+        Ok(counts)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn start_watching(
+        &mut self,
+        _sender: mpsc::Sender<DiskoEvent>,
+    ) -> Result<(usize, usize)> {
+        bail!("Watching is not supported on this platform")
+    }
+
+    /// Apply the changes collected by the watcher to the tree. The changes
+    /// are postponed while entries are being deleted or rescanned.
+    /// Returns whether the tree has changed.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn apply_watched_changes(&mut self) -> bool {
+        if self.deletion_handler.is_some() || self.is_traversing() {
+            return false;
+        }
+        let Some(watcher) = &self.watcher else {
+            return false;
+        };
+        let changes = watcher.take_changes();
+        if changes.is_empty() {
+            return false;
+        }
+
+        for path in changes {
+            self.sync_entry(&path);
+        }
+        self.invalidate_largest_files();
+        self.leave_removed_directory();
+        true
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn apply_watched_changes(&mut self) -> bool {
+        false
+    }
+
+    /// Whether the watcher has lost events since the last call, leaving
+    /// sizes which may not match the file system until it is rescanned.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn watch_events_lost(&self) -> bool {
+        self.watcher
+            .as_ref()
+            .is_some_and(|watcher| watcher.take_overflow())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub(crate) fn watch_events_lost(&self) -> bool {
+        false
+    }
+
+    /// Move the entries of the batch from the trash back to their original
+    /// paths and attach them to the tree again.
+    /// The entries which could not be restored are kept in the report.
+    pub(crate) fn restore_batch(&self, batch: DeletionBatch) -> RestoreReport {
+        let mut report = RestoreReport {
+            restored: 0,
+            failed: DeletionBatch::default(),
+            errors: Vec::new(),
+        };
+
+        // Restore in the reverse order of deletion.
+        for deleted in batch.entries.into_iter().rev() {
+            match self.restore_entry(deleted) {
+                Ok(()) => report.restored += 1,
+                Err((deleted, error)) => {
+                    report.failed.entries.extend(deleted);
+                    report.errors.push(error);
+                }
+            }
+        }
+        // Keep the order of deletion for the next attempt.
+        report.failed.entries.reverse();
+        self.invalidate_largest_files();
+        report
+    }
 }
 
 // Convenience/helpers
 
 impl DiskoTree {
+    fn spawn_traversal(
+        &mut self,
+        root: PathBuf,
+        ancestor: TreeWalkAncestor,
+        excluded_count: Arc<AtomicUsize>,
+        largest_files: SharedLargestFiles,
+        counted_hard_links: HashSet<u64>,
+        sender: mpsc::Sender<DiskoEvent>,
+    ) {
+        let tree = self.tree.clone();
+        let is_traversing = self.is_traversing.clone();
+        let stop_traversing = self.stop_traversing.clone();
+        let traversal_threads = self.traversal_threads;
+        let traversal_options = self.traversal_options.clone();
+        let errors = self.traversal_errors.clone();
+        // Set before spawning, so that another traversal cannot be started
+        // in the meantime.
+        is_traversing.store(true, Ordering::Release);
+        self.traversal_handler = Some(thread::spawn(move || {
+            let walk = Self::jwalk_walk_dir(
+                root,
+                ancestor,
+                traversal_threads,
+                traversal_options,
+                excluded_count,
+                errors.clone(),
+                largest_files,
+                counted_hard_links,
+            );
+            Self::run_walk(walk, &tree, &errors, Some(&stop_traversing));
+
+            is_traversing.store(false, Ordering::Release);
+            // Here we just ignore if the event handler has stopped.
+            let _ = sender.send(DiskoEvent::TraversalFinished);
+        }));
+    }
+
+    fn spawn_deletion(
+        &mut self,
+        entries: Vec<Arc<RwLock<Node<EntryNode>>>>,
+        deletion_mode: DeletionMode,
+        sender: mpsc::Sender<DiskoEvent>,
+    ) {
+        let worker = DeletionWorker {
+            tree: self.tree.clone(),
+            entries,
+            deletion_mode,
+            traversal_threads: self.traversal_threads,
+            traversal_options: self.traversal_options.clone(),
+            traversal_errors: self.traversal_errors.clone(),
+            cancel: self.stop_deleting.clone(),
+            sender,
+        };
+        self.stop_deleting.store(false, Ordering::Relaxed);
+        self.deletion_handler = Some(thread::spawn(move || worker.run()));
+    }
+
+    fn invalidate_largest_files(&self) {
+        self.largest_files
+            .lock()
+            .expect("Failed to lock largest files")
+            .invalidate();
+    }
+
+    /// The inodes of the hard linked files whose size is counted in the tree.
+    /// A directory scanned again after it has been detached counts these
+    /// files as already seen, their size would be counted twice otherwise.
+    pub(super) fn counted_hard_links(tree: &Arc<RwLock<Tree<EntryNode>>>) -> HashSet<u64> {
+        let mut inodes = HashSet::new();
+        let Some(root) = tree.read().expect("Failed to read tree").get_root() else {
+            return inodes;
+        };
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            let node = node
+                .read()
+                .expect("Failed to read node while collecting hard links");
+            stack.extend(node.get_children());
+            let entry = &node.data;
+            // Only one of the links carries the size, the others are empty.
+            if matches!(entry.entry_type, EntryType::File)
+                && entry.metadata.links > 1
+                && entry.sizes.inodes > 0
+            {
+                inodes.insert(entry.metadata.inode);
+            }
+        }
+        inodes
+    }
+
+    /// Traverse the directory at the given path into a new detached subtree.
+    /// The hard linked files already counted in the tree are counted as
+    /// empty. Returns `None` if the directory could not be read.
+    pub(super) fn scan_subtree(
+        path: &Path,
+        traversal_threads: usize,
+        traversal_options: Arc<TraversalOptions>,
+        errors: &TraversalErrors,
+        counted_hard_links: HashSet<u64>,
+    ) -> Option<Arc<RwLock<Node<EntryNode>>>> {
+        let tree = Arc::new(RwLock::new(Tree::new()));
+        // The excluded entries have already been counted by the traversal.
+        let walk = Self::jwalk_walk_dir(
+            path.to_path_buf(),
+            TreeWalkAncestor::Tree(tree.clone()),
+            traversal_threads,
+            traversal_options,
+            Arc::default(),
+            errors.clone(),
+            // The largest files are collected again once they are needed.
+            Arc::new(Mutex::new(LargestFiles::new(0))),
+            counted_hard_links,
+        );
+        Self::run_walk(walk, &tree, errors, None);
+        let root = tree
+            .read()
+            .expect("Failed to read the rescanned tree")
+            .get_root();
+        root
+    }
+
+    /// Drive the traversal to its end, unless it is stopped.
+    ///
+    /// Directories which cannot be read are only reported on the entries
+    /// yielded by jwalk, `process_dir` is never called for them. Errors of
+    /// the other entries are recorded by `process_dir`.
+    fn run_walk(
+        walk: WalkDirGeneric<CustomJWalkClientState>,
+        tree: &Arc<RwLock<Tree<EntryNode>>>,
+        errors: &TraversalErrors,
+        stop: Option<&AtomicBool>,
+    ) {
+        for dir_entry in walk.into_iter().filter_map(std::result::Result::ok) {
+            if stop.is_some_and(|stop| stop.load(Ordering::Relaxed)) {
+                break;
+            }
+            if let Some(error) = &dir_entry.read_children_error {
+                errors
+                    .lock()
+                    .expect("Failed to lock traversal errors")
+                    .push(TraversalError {
+                        path: dir_entry.path(),
+                        kind: error_kind(error),
+                    });
+                Self::attach_unreadable_dir(tree, &dir_entry.path());
+            }
+        }
+    }
+
+    /// Add the directory whose contents could not be read to the tree and
+    /// mark it as incomplete.
+    fn attach_unreadable_dir(tree: &Arc<RwLock<Tree<EntryNode>>>, path: &Path) {
+        let parent = path
+            .parent()
+            .and_then(|parent| Self::find_node(tree, parent));
+        let Some((dir_node, dir_size)) = EntryNode::new_dir(path) else {
+            if let Some(parent) = parent {
+                Self::mark_incomplete(&parent);
+            }
+            return;
+        };
+
+        let node = match parent {
+            Some(parent) => Tree::attach_child(&parent, dir_node),
+            // The traversal root itself could not be read.
+            None => match tree
+                .write()
+                .expect("Failed to write to tree")
+                .create_node_and_set_root(dir_node)
+            {
+                Ok(node) => node,
+                Err(_) => return,
+            },
+        };
+        // The directory has already been counted by its parent.
+        Self::backprop_size(&node, dir_size, 0, BackpropOperation::Add);
+        Self::mark_incomplete(&node);
+    }
+
+    /// Find the node of the entry at the given path.
+    pub(crate) fn find_node(
+        tree: &Arc<RwLock<Tree<EntryNode>>>,
+        path: &Path,
+    ) -> Option<Arc<RwLock<Node<EntryNode>>>> {
+        let root = tree.read().expect("Failed to read tree").get_root()?;
+        let root_path = root.read().expect("Failed to read root").data.path.clone();
+        path.strip_prefix(root_path)
+            .ok()?
+            .components()
+            .try_fold(root, |node, component| {
+                Self::find_child(&node, component.as_os_str())
+            })
+    }
+
+    /// Find the child of the node with the given name.
+    pub(super) fn find_child(
+        node: &Arc<RwLock<Node<EntryNode>>>,
+        name: &OsStr,
+    ) -> Option<Arc<RwLock<Node<EntryNode>>>> {
+        let name = name.to_string_lossy();
+        node.read()
+            .expect("Failed to read node while looking for child")
+            .get_children()
+            .into_iter()
+            .find(|child| {
+                child
+                    .read()
+                    .expect("Failed to read child while looking for child")
+                    .data
+                    .name
+                    == name
+            })
+    }
+
+    /// Mark the node and all of its ancestors as having incomplete sizes.
+    fn mark_incomplete(node: &Arc<RwLock<Node<EntryNode>>>) {
+        for node in Tree::iter_to_root_from_node(node.clone()) {
+            let mut node = node
+                .write()
+                .expect("Failed to write while marking incomplete");
+            // The ancestors of an incomplete node are already marked.
+            if node.data.incomplete {
+                break;
+            }
+            node.data.incomplete = true;
+        }
+    }
+
+    /// Restore the entry and attach it to the tree again. Fails with the
+    /// entry if it is still in the trash, so that it can be tried again, and
+    /// without it if it has been restored but could not be attached.
+    fn restore_entry(
+        &self,
+        deleted: DeletedEntry,
+    ) -> std::result::Result<(), (Option<DeletedEntry>, anyhow::Error)> {
+        let path = deleted.trashed.original_path.clone();
+        if let Err(error) = trash::restore(&deleted.trashed) {
+            let error = anyhow::Error::new(error)
+                .context(format!("Failed to restore '{}'", path.display()));
+            return Err((Some(deleted), error));
+        }
+
+        // A rescan replaces the nodes of the rescanned directories, the
+        // parent is then looked up again by its path.
+        let parent = if self.is_in_tree(&deleted.parent) {
+            Some(deleted.parent)
+        } else {
+            path.parent()
+                .and_then(|parent| Self::find_node(&self.tree, parent))
+        };
+        let Some(parent) = parent else {
+            return Err((
+                None,
+                anyhow!(
+                    "Restored '{}', but its directory is no longer scanned",
+                    path.display()
+                ),
+            ));
+        };
+
+        let (size, descendants_count) = {
+            let node = deleted.node.read().expect("Failed to read restored node");
+            (node.data.sizes, node.data.descendants_count)
+        };
+        Tree::attach_subtree(&parent, deleted.node)
+            .expect("Deleted node should have been detached from its parent");
+        Self::backprop_size(&parent, size, descendants_count + 1, BackpropOperation::Add);
+        Ok(())
+    }
+
+    /// Watch the directories of the subtree until the limit of watches is
+    /// reached. Mount points are not watched as they are not traversed.
+    /// Returns the number of watched directories and the number of all
+    /// directories.
+    #[cfg(target_os = "linux")]
+    fn watch_subtree(
+        watcher: &mut Watcher,
+        subtree: &Arc<RwLock<Node<EntryNode>>>,
+    ) -> (usize, usize) {
+        let (mut watched, mut total) = (0, 0);
+        let mut limit_reached = false;
+        let mut stack = vec![subtree.clone()];
+        while let Some(node) = stack.pop() {
+            let node = node.read().expect("Failed to read node while watching");
+            if !matches!(node.data.entry_type, EntryType::Directory) || node.data.mount_point {
+                continue;
+            }
+            total += 1;
+            stack.extend(node.get_children());
+            if limit_reached {
+                continue;
+            }
+            match watcher.watch(&node.data.path) {
+                Ok(()) => watched += 1,
+                Err(error) if error.kind() == io::ErrorKind::StorageFull => limit_reached = true,
+                // The directory may have vanished since it was scanned.
+                Err(_) => {}
+            }
+        }
+        (watched, total)
+    }
+
+    /// Make the entry at the given path in the tree match the file system.
+    #[cfg(target_os = "linux")]
+    fn sync_entry(&mut self, path: &Path) {
+        if self.traversal_options.is_excluded(path) {
+            return;
+        }
+        let metadata = fs::symlink_metadata(path).ok();
+        let node = match (Self::find_node(&self.tree, path), &metadata) {
+            // The entry has been replaced by an entry of another type.
+            (Some(node), Some(metadata))
+                if matches!(
+                    node.read().expect("Failed to read node").data.entry_type,
+                    EntryType::Directory
+                ) != metadata.is_dir() =>
+            {
+                self.remove_watched(&node);
+                None
+            }
+            (node, _) => node,
+        };
+
+        match (node, metadata) {
+            (Some(node), None) => self.remove_watched(&node),
+            (None, Some(metadata)) => self.add_watched(path, &metadata),
+            (Some(node), Some(metadata)) => Self::update_file(&node, &metadata),
+            (None, None) => {}
+        }
+    }
+
+    /// Add the new entry to the tree, new directories are scanned and
+    /// watched.
+    #[cfg(target_os = "linux")]
+    fn add_watched(&mut self, path: &Path, metadata: &fs::Metadata) {
+        let Some(parent) = path
+            .parent()
+            .and_then(|parent| Self::find_node(&self.tree, parent))
+        else {
+            return;
+        };
+
+        if !metadata.is_dir() {
+            let file = EntryNode::new_file(path, metadata);
+            let size = file.sizes;
+            Tree::attach_child(&parent, file);
+            Self::backprop_size(&parent, size, 1, BackpropOperation::Add);
+            return;
+        }
+
+        let Some(subtree) = Self::scan_subtree(
+            path,
+            self.traversal_threads,
+            self.traversal_options.clone(),
+            &self.traversal_errors,
+            Self::counted_hard_links(&self.tree),
+        ) else {
+            return;
+        };
+        let (size, descendants_count) = {
+            let subtree = subtree.read().expect("Failed to read scanned subtree");
+            (subtree.data.sizes, subtree.data.descendants_count)
+        };
+        Tree::attach_subtree(&parent, subtree.clone())
+            .expect("A freshly scanned subtree should have no parent");
+        Self::backprop_size(&parent, size, descendants_count + 1, BackpropOperation::Add);
+        if let Some(watcher) = &mut self.watcher {
+            Self::watch_subtree(watcher, &subtree);
+        }
+    }
+
+    /// Remove the vanished entry from the tree and stop watching it.
+    #[cfg(target_os = "linux")]
+    fn remove_watched(&mut self, node: &Arc<RwLock<Node<EntryNode>>>) {
+        let (path, has_parent) = {
+            let node = node.read().expect("Failed to read node");
+            (node.data.path.clone(), node.get_parent().is_some())
+        };
+        // The root stays in the tree even if it vanishes.
+        if !has_parent {
+            return;
+        }
+        if let Some(watcher) = &mut self.watcher {
+            watcher.unwatch_subtree(&path);
+        }
+        Self::detach_node(&self.tree, node);
+    }
+
+    /// Update the size of the modified file.
+    #[cfg(target_os = "linux")]
+    fn update_file(node: &Arc<RwLock<Node<EntryNode>>>, metadata: &fs::Metadata) {
+        let (old_size, new_size) = {
+            let mut node = node.write().expect("Failed to write node");
+            // Hard linked files are counted only once, keep the size they
+            // have been counted with.
+            if !matches!(node.data.entry_type, EntryType::File) || node.data.metadata.links > 1 {
+                return;
+            }
+            node.data.metadata = metadata.into();
+            (node.data.sizes, EntrySize::new(&node.data.path, metadata))
+        };
+        Self::backprop_size(node, old_size, 0, BackpropOperation::Subtract);
+        Self::backprop_size(node, new_size, 0, BackpropOperation::Add);
+    }
+
+    /// Switch to the closest ancestor still in the tree if the current
+    /// directory has been removed.
+    fn leave_removed_directory(&mut self) {
+        let Some(current_directory) = &self.current_directory else {
+            return;
+        };
+        if self.is_in_tree(current_directory) {
+            return;
+        }
+        let path = current_directory
+            .read()
+            .expect("Failed to read current directory")
+            .data
+            .path
+            .clone();
+        self.current_directory = path
+            .ancestors()
+            .find_map(|ancestor| Self::find_node(&self.tree, ancestor));
+    }
+
+    /// Switch to the node replacing the current directory once it is
+    /// attached again by a rescan of the directory or one of its ancestors.
+    /// The old node is shown until then.
+    fn follow_rescanned_directory(&mut self) {
+        let Some(current_directory) = &self.current_directory else {
+            return;
+        };
+        if self.is_in_tree(current_directory) {
+            return;
+        }
+        let path = current_directory
+            .read()
+            .expect("Failed to read current directory")
+            .data
+            .path
+            .clone();
+        if let Some(node) = Self::find_node(&self.tree, &path) {
+            self.current_directory = Some(node);
+        } else if !self.is_traversing() {
+            // The directory was removed before the rescan.
+            self.leave_removed_directory();
+        }
+    }
+
+    /// Remove the node from the tree and subtract its size from its
+    /// ancestors. Returns the size of the node.
+    pub(super) fn detach_node(
+        tree: &Arc<RwLock<Tree<EntryNode>>>,
+        node: &Arc<RwLock<Node<EntryNode>>>,
+    ) -> EntrySize {
+        let (parent, size, descendants_count) = {
+            let node = node
+                .read()
+                .expect("Failed to read node while detaching it.");
+            (
+                node.get_parent().and_then(|parent| parent.upgrade()),
+                node.data.sizes,
+                node.data.descendants_count,
+            )
+        };
+        tree.write()
+            .expect("Failed to write to tree while detaching node.")
+            .remove_subtree(node)
+            .expect("Failed to detach node.");
+        if let Some(parent) = parent {
+            Self::backprop_size(
+                &parent,
+                size,
+                descendants_count + 1,
+                BackpropOperation::Subtract,
+            );
+        }
+        size
+    }
+
+    /// Check whether the node is still connected to the root of the tree.
+    fn is_in_tree(&self, node: &Arc<RwLock<Node<EntryNode>>>) -> bool {
+        let Some(root) = self
+            .tree
+            .read()
+            .expect("Failed to read the underlying tree in diskotree")
+            .get_root()
+        else {
+            return false;
+        };
+        Tree::iter_to_root_from_node(node.clone())
+            .last()
+            .is_some_and(|top| Arc::ptr_eq(&top, &root))
+    }
+
     fn process_dir(
         depth: Option<usize>,
         dir_path: &Path,
         state: &mut TreeWalkState,
-        children: &mut [jwalk::Result<DirEntry<CustomJWalkClientState>>],
+        children: &mut Vec<jwalk::Result<DirEntry<CustomJWalkClientState>>>,
     ) {
         // Skip parent directory (./..).
         if depth.is_none() {
             return;
         }
+        // Drop the excluded entries, so that jwalk does not descend into them.
+        children.retain(|dir_entry_result| match dir_entry_result {
+            Ok(dir_entry) => !state.exclude(&dir_entry.path()),
+            Err(_) => true,
+        });
         // Create entry node from jwalks
         let Some((dir_node, dir_size)) = EntryNode::new_dir(dir_path) else {
+            let kind =
+                fs::metadata(dir_path).map_or_else(|error| error.kind(), |_| io::ErrorKind::Other);
+            state.record_error(dir_path.to_path_buf(), kind);
+            if let TreeWalkAncestor::Parent(parent) = &state.ancestor {
+                Self::mark_incomplete(parent);
+            }
             return;
         };
 
@@ -319,28 +1471,75 @@ impl DiskoTree {
         // Create node on tree.
         let node = Self::attach_to_tree(state, dir_node);
 
-        children
-            .iter_mut()
-            // Put reference to results inner types.
-            .map(|dir_entry_result| dir_entry_result.as_ref())
-            // Filter errors out and return just `DirEntry` entries.
-            .filter_map(std::result::Result::ok)
-            .filter(|dir_entry| dir_entry.file_type.is_file())
-            // Map to our `EntryNode`s.
-            .map(EntryNode::try_from)
-            // Throw away when convertion failed.
-            .filter_map(Result::ok)
-            // Finaly process the file children.
-            .for_each(|mut child_node| {
-                if state.file_has_been_seen(&child_node.metadata) {
-                    child_node.sizes = EntrySize::default();
+        // Do not descend into mount points when staying on one file system,
+        // but keep them in the listing. Looking for them costs a call to
+        // lstat for every directory, so only do it when asked to.
+        if state.stays_on_one_file_system() {
+            children
+                .iter_mut()
+                .filter_map(|dir_entry_result| dir_entry_result.as_mut().ok())
+                .filter(|dir_entry| dir_entry.file_type.is_dir())
+                .for_each(|dir_entry| {
+                    let Ok(metadata) = dir_entry.metadata() else {
+                        return;
+                    };
+                    if !state.is_on_other_file_system(&metadata) {
+                        return;
+                    }
+                    dir_entry.read_children_path = None;
+                    match EntryNode::try_from(&*dir_entry) {
+                        Ok(mount_point) => {
+                            Tree::attach_child(&node, mount_point.into_mount_point());
+                        }
+                        Err(error) => state.record_error(dir_entry.path(), error_kind(&error)),
+                    }
+                });
+        }
+
+        // Every child directory is counted here, their contents are counted
+        // once they are processed.
+        let mut items = children
+            .iter()
+            .filter_map(|dir_entry_result| dir_entry_result.as_ref().ok())
+            .filter(|dir_entry| dir_entry.file_type.is_dir())
+            .count();
+
+        // Process the file children, remembering the entries which could not
+        // be read.
+        let mut incomplete = false;
+        for dir_entry_result in children.iter() {
+            let dir_entry = match dir_entry_result {
+                Ok(dir_entry) if dir_entry.file_type.is_file() => dir_entry,
+                Ok(_) => continue,
+                Err(error) => {
+                    let path = error.path().unwrap_or(dir_path).to_path_buf();
+                    state.record_error(path, error_kind(error));
+                    incomplete = true;
+                    continue;
+                }
+            };
+            match EntryNode::try_from(dir_entry) {
+                Ok(mut child_node) => {
+                    if state.file_has_been_seen(&child_node.metadata) {
+                        child_node.sizes = EntrySize::default();
+                    }
+                    size += child_node.sizes;
+                    items += 1;
+                    state.offer_largest_file(&child_node);
+                    Tree::attach_child(&node, child_node);
                 }
-                size += child_node.sizes;
-                Tree::attach_child(&node, child_node);
-            });
+                Err(error) => {
+                    state.record_error(dir_entry.path(), error_kind(&error));
+                    incomplete = true;
+                }
+            }
+        }
+        if incomplete {
+            Self::mark_incomplete(&node);
+        }
 
         // Propagate size up including this node to root (including).
-        Self::backprop_size(&node, size, BackpropOperation::Add);
+        Self::backprop_size(&node, size, items, BackpropOperation::Add);
 
         // Move (i.e. not .clone()) reference to this node as a parent
         // for the next iteration.
@@ -360,9 +1559,12 @@ impl DiskoTree {
         }
     }
 
-    fn backprop_size(
+    /// Add or subtract the size and the number of descendants from the node
+    /// and all of its ancestors.
+    pub(super) fn backprop_size(
         node: &Arc<RwLock<Node<EntryNode>>>,
         size: EntrySize,
+        descendants_count: usize,
         operation: BackpropOperation,
     ) {
         let iter = Tree::iter_to_root_from_node(node.clone());
@@ -373,8 +1575,14 @@ impl DiskoTree {
                 .expect("Failed to write while backpropagating size");
 
             match operation {
-                BackpropOperation::Add => node.data.sizes += size,
-                BackpropOperation::Subtract => node.data.sizes -= size,
+                BackpropOperation::Add => {
+                    node.data.sizes += size;
+                    node.data.descendants_count += descendants_count;
+                }
+                BackpropOperation::Subtract => {
+                    node.data.sizes -= size;
+                    node.data.descendants_count -= descendants_count;
+                }
             };
         });
     }
@@ -410,3 +1618,24 @@ impl fmt::Display for DiskoTree {
         write!(f, "\n└── {}", last.data)
     }
 }
+
+fn error_kind(error: &jwalk::Error) -> io::ErrorKind {
+    error
+        .io_error()
+        .map_or(io::ErrorKind::Other, io::Error::kind)
+}
+
+#[cfg(test)]
+impl DiskoTree {
+    /// Attach the entry to the parent the way the traversal does, adding its
+    /// size and descendants to the parent and its ancestors.
+    pub(super) fn attach_for_test(
+        parent: &Arc<RwLock<Node<EntryNode>>>,
+        entry: EntryNode,
+    ) -> Arc<RwLock<Node<EntryNode>>> {
+        let (size, descendants_count) = (entry.sizes, entry.descendants_count);
+        let node = Tree::attach_child(parent, entry);
+        Self::backprop_size(parent, size, descendants_count + 1, BackpropOperation::Add);
+        node
+    }
+}
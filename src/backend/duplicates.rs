@@ -0,0 +1,323 @@
+//! Finding files with the same contents in the scanned tree.
+//!
+//! Files are grouped by their size first, then by a hash of their first
+//! bytes and only then by a hash of their whole contents, so that most files
+//! are never read completely.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    hash::Hash,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use ref_tree::Node;
+use xxhash_rust::xxh3::Xxh3;
+
+use super::entry_node::{EntryNode, EntryType};
+
+/// How many bytes from the start of a file are hashed to tell files of the
+/// same size apart.
+const PARTIAL_HASH_SIZE: u64 = 16 * 1024;
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Files with the same contents.
+#[derive(Clone, Debug)]
+pub struct DuplicateGroup {
+    /// The apparent size of each of the files.
+    pub size: u64,
+    /// The paths of the files, sorted.
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateGroup {
+    /// The space which would be freed by keeping only one of the files.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.paths.len() as u64 - 1)
+    }
+}
+
+/// Find the groups of files with the same contents in the subtree. The
+/// groups wasting the most space come first.
+///
+/// Empty files are not reported, and neither are multiple hard links to the
+/// same file as removing them would not free any space.
+/// Returns `None` if the search has been cancelled.
+pub(super) fn find_duplicates(
+    root: &Arc<RwLock<Node<EntryNode>>>,
+    cancel: &AtomicBool,
+) -> Option<Vec<DuplicateGroup>> {
+    let by_size = group_by(collect_files(root), |(_, size)| Some(*size));
+
+    let mut groups = Vec::new();
+    for same_size in by_size {
+        let size = same_size[0].1;
+        let paths: Vec<PathBuf> = same_size.into_iter().map(|(path, _)| path).collect();
+
+        let by_partial_hash = group_by(paths, |path| {
+            (!cancel.load(Ordering::Relaxed)).then(|| hash_file(path, Some(PARTIAL_HASH_SIZE)))?
+        });
+        for same_partial_hash in by_partial_hash {
+            // The partial hash already covers the whole contents of small
+            // files.
+            let same_contents = if size <= PARTIAL_HASH_SIZE {
+                vec![same_partial_hash]
+            } else {
+                group_by(same_partial_hash, |path| {
+                    (!cancel.load(Ordering::Relaxed)).then(|| hash_file(path, None))?
+                })
+            };
+            groups.extend(
+                same_contents
+                    .into_iter()
+                    .map(|paths| DuplicateGroup { size, paths }),
+            );
+        }
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+    }
+
+    for group in &mut groups {
+        group.paths.sort();
+    }
+    groups.sort_by(|a, b| {
+        b.wasted()
+            .cmp(&a.wasted())
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    Some(groups)
+}
+
+/// Collect the paths and apparent sizes of all non-empty files, each hard
+/// linked file only once.
+fn collect_files(root: &Arc<RwLock<Node<EntryNode>>>) -> Vec<(PathBuf, u64)> {
+    let mut files = Vec::new();
+    let mut seen_inodes = HashSet::new();
+    let mut stack = vec![root.clone()];
+    while let Some(node) = stack.pop() {
+        let node = node
+            .read()
+            .expect("Failed to read node while finding duplicates");
+        stack.extend(node.get_children());
+
+        let entry = &node.data;
+        if !matches!(entry.entry_type, EntryType::File) || entry.sizes.apparent_size == 0 {
+            continue;
+        }
+        let metadata = &entry.metadata;
+        if metadata.links > 1
+            && metadata.inode != 0
+            && !seen_inodes.insert((metadata.device, metadata.inode))
+        {
+            continue;
+        }
+        files.push((entry.path.clone(), entry.sizes.apparent_size));
+    }
+    files
+}
+
+/// Split the items into groups with the same key, keeping only the groups
+/// with more than one item. Items without a key are dropped.
+fn group_by<T, K: Eq + Hash>(items: Vec<T>, key: impl Fn(&T) -> Option<K>) -> Vec<Vec<T>> {
+    let mut groups: HashMap<K, Vec<T>> = HashMap::new();
+    for item in items {
+        if let Some(key) = key(&item) {
+            groups.entry(key).or_default().push(item);
+        }
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+/// Hash the contents of the file, up to the given number of bytes.
+/// Returns `None` if the file could not be read, such files are not
+/// reported.
+fn hash_file(path: &Path, limit: Option<u64>) -> Option<u128> {
+    let file = File::open(path).ok()?;
+    let mut reader: Box<dyn Read> = match limit {
+        Some(limit) => Box::new(file.take(limit)),
+        None => Box::new(file),
+    };
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0; READ_BUFFER_SIZE];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return None,
+        }
+    }
+    Some(hasher.digest128())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use ref_tree::Tree;
+
+    use super::*;
+
+    /// A scanned directory in the temporary directory, removed when dropped.
+    struct TestDir {
+        path: PathBuf,
+        tree: Tree<EntryNode>,
+    }
+
+    impl TestDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("disko-duplicates-{}-{name}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            let mut tree = Tree::new();
+            tree.create_node_and_set_root(EntryNode::for_test(
+                path.to_str().unwrap(),
+                EntryType::Directory,
+                0,
+            ))
+            .unwrap();
+            Self { path, tree }
+        }
+
+        /// Write the file, returning its entry without adding it to the tree.
+        fn write(&self, name: &str, contents: &[u8]) -> EntryNode {
+            let path = self.path.join(name);
+            fs::write(&path, contents).unwrap();
+            EntryNode::for_test(
+                path.to_str().unwrap(),
+                EntryType::File,
+                contents.len() as u64,
+            )
+        }
+
+        fn add(&self, entry: EntryNode) {
+            Tree::attach_child(&self.tree.get_root().unwrap(), entry);
+        }
+
+        fn file(&self, name: &str, contents: &[u8]) {
+            self.add(self.write(name, contents));
+        }
+
+        /// The file names in each group of duplicates.
+        fn find(&self) -> Vec<Vec<String>> {
+            let cancel = AtomicBool::new(false);
+            find_duplicates(&self.tree.get_root().unwrap(), &cancel)
+                .unwrap()
+                .into_iter()
+                .map(|group| {
+                    group
+                        .paths
+                        .iter()
+                        .map(|path| path.file_name().unwrap().to_str().unwrap().to_string())
+                        .collect()
+                })
+                .collect()
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+
+    fn hard_link(mut entry: EntryNode, inode: u64) -> EntryNode {
+        entry.metadata.device = 1;
+        entry.metadata.inode = inode;
+        entry.metadata.links = 2;
+        entry
+    }
+
+    #[test]
+    fn test_same_contents() {
+        let dir = TestDir::new("same_contents");
+        dir.file("a", b"hello");
+        dir.file("b", b"hello");
+        dir.file("c", b"world");
+        dir.file("d", b"hello!");
+        assert_eq!(dir.find(), vec![vec!["a", "b"]]);
+    }
+
+    #[test]
+    fn test_empty_files_are_skipped() {
+        let dir = TestDir::new("empty_files");
+        dir.file("a", b"");
+        dir.file("b", b"");
+        assert!(dir.find().is_empty());
+    }
+
+    #[test]
+    fn test_large_files_differing_after_partial_hash() {
+        let dir = TestDir::new("large_files");
+        let contents = vec![7; PARTIAL_HASH_SIZE as usize * 2];
+        let mut different = contents.clone();
+        *different.last_mut().unwrap() = 8;
+        dir.file("a", &contents);
+        dir.file("b", &different);
+        dir.file("c", &contents);
+        assert_eq!(dir.find(), vec![vec!["a", "c"]]);
+    }
+
+    #[test]
+    fn test_hard_links_count_once() {
+        let dir = TestDir::new("hard_links");
+        dir.add(hard_link(dir.write("a", b"hello"), 42));
+        dir.add(hard_link(dir.write("b", b"hello"), 42));
+        assert!(dir.find().is_empty());
+
+        dir.file("c", b"hello");
+        let groups = dir.find();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].len(), 2);
+        assert!(groups[0].contains(&"c".to_string()));
+    }
+
+    #[test]
+    fn test_most_wasted_first() {
+        let dir = TestDir::new("most_wasted");
+        dir.file("small1", b"ab");
+        dir.file("small2", b"ab");
+        dir.file("small3", b"ab");
+        dir.file("large1", b"abcdefgh");
+        dir.file("large2", b"abcdefgh");
+        assert_eq!(
+            dir.find(),
+            vec![vec!["large1", "large2"], vec!["small1", "small2", "small3"]]
+        );
+    }
+
+    #[test]
+    fn test_unreadable_files_are_skipped() {
+        let dir = TestDir::new("unreadable");
+        dir.file("a", b"hello");
+        dir.file("b", b"hello");
+        fs::remove_file(dir.path.join("b")).unwrap();
+        assert!(dir.find().is_empty());
+    }
+
+    #[test]
+    fn test_cancel() {
+        let dir = TestDir::new("cancel");
+        dir.file("a", b"hello");
+        dir.file("b", b"hello");
+        let cancel = AtomicBool::new(true);
+        assert!(find_duplicates(&dir.tree.get_root().unwrap(), &cancel).is_none());
+    }
+
+    #[test]
+    fn test_group_by() {
+        let mut groups = group_by(vec![1, 2, 3, 4, 5, 6], |n| (*n != 5).then_some(n % 3));
+        groups.sort();
+        assert_eq!(groups, vec![vec![1, 4], vec![3, 6]]);
+    }
+}
@@ -2,13 +2,20 @@ use std::{
     fmt::Display,
     fs::{self, Metadata},
     path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
 
-use super::{entry_size::EntrySize, tree_walk_state::CustomJWalkClientState};
+use super::{
+    disko_tree::DeletionMode,
+    entry_size::{EntrySize, SizeChange, SizeMetric},
+    trash::{self, TrashedEntry},
+    tree_walk_state::CustomJWalkClientState,
+};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum EntryType {
     Directory,
     File,
@@ -23,7 +30,16 @@ pub(crate) struct EntryNode {
     pub(crate) dir_size: Option<EntrySize>,
     pub(crate) descendants_count: usize,
     pub(crate) entry_type: EntryType,
-    pub(crate) metadata: fs::Metadata,
+    pub(crate) metadata: EntryMetadata,
+    /// Whether the entry is a directory on another file system which was not
+    /// traversed.
+    pub(crate) mount_point: bool,
+    /// Whether some entries in the subtree could not be read, so the sizes
+    /// are lower than they should be.
+    pub(crate) incomplete: bool,
+    /// How the entry has changed since an earlier scan, only set when
+    /// comparing two scans.
+    pub(crate) change: Option<SizeChange>,
 }
 
 pub struct EntryNodeView {
@@ -33,17 +49,59 @@ pub struct EntryNodeView {
     pub dir_size: Option<EntrySize>,
     pub descendants_count: usize,
     pub entry_type: EntryType,
+    pub mount_point: bool,
+    pub incomplete: bool,
+    pub change: Option<SizeChange>,
     pub mode: Mode,
     pub access_time: Option<DateTime<Local>>,
+    pub modified: Option<SystemTime>,
     pub index_to_original_node: Option<usize>,
 }
 
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
 pub enum Mode {
     Permissions(u32),
     Attributes(u32),
+    #[default]
     Unknown,
 }
 
+/// The part of the file system metadata kept for each entry.
+///
+/// Entries do not keep `fs::Metadata` as it can only be obtained from the
+/// file system, while entries can also be imported.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct EntryMetadata {
+    pub(crate) mode: Mode,
+    pub(crate) modified: Option<SystemTime>,
+    pub(crate) accessed: Option<SystemTime>,
+    /// Id of the device the entry is stored on, 0 if unknown.
+    pub(crate) device: u64,
+    /// Inode number of the entry, 0 if unknown.
+    pub(crate) inode: u64,
+    /// Number of hard links to the entry.
+    pub(crate) links: u64,
+}
+
+impl Mode {
+    /// The raw mode bits, if known.
+    pub fn bits(&self) -> Option<u32> {
+        match self {
+            Mode::Permissions(bits) | Mode::Attributes(bits) => Some(*bits),
+            Mode::Unknown => None,
+        }
+    }
+}
+
+impl EntryMetadata {
+    /// The modification time in seconds since the Unix epoch.
+    pub(crate) fn modified_timestamp(&self) -> Option<u64> {
+        self.modified
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|modified| modified.as_secs())
+    }
+}
+
 impl EntryNodeView {
     pub(crate) fn new_dir(path: PathBuf) -> Self {
         Self {
@@ -53,10 +111,14 @@ impl EntryNodeView {
             dir_size: Some(EntrySize::default()),
             descendants_count: 0,
             entry_type: EntryType::Directory,
+            mount_point: false,
+            incomplete: false,
+            change: None,
             // Unknown here for now, this needs to be updated later during the
             // backend refactor.
             mode: Mode::Unknown,
             access_time: None,
+            modified: None,
             index_to_original_node: None,
         }
     }
@@ -69,12 +131,12 @@ impl EntryNodeView {
             dir_size: entry_node.dir_size,
             descendants_count: entry_node.descendants_count,
             entry_type: entry_node.entry_type,
-            access_time: entry_node
-                .metadata
-                .accessed()
-                .ok()
-                .map(DateTime::<Local>::from),
-            mode: extract_mode(&entry_node.metadata),
+            mount_point: entry_node.mount_point,
+            incomplete: entry_node.incomplete,
+            change: entry_node.change,
+            access_time: entry_node.metadata.accessed.map(DateTime::<Local>::from),
+            mode: entry_node.metadata.mode,
+            modified: entry_node.metadata.modified,
             index_to_original_node: None,
         }
     }
@@ -102,16 +164,57 @@ impl EntryNode {
                 dir_size: Some(size),
                 descendants_count: 0,
                 entry_type: EntryType::Directory,
-                metadata,
+                metadata: EntryMetadata::from(&metadata),
+                mount_point: false,
+                incomplete: false,
+                change: None,
             },
             size,
         ))
     }
 
-    pub(crate) fn delete_entry(&self) -> std::io::Result<()> {
-        match self.entry_type {
-            EntryType::Directory => std::fs::remove_dir_all(self.path.clone()),
-            EntryType::File => std::fs::remove_file(self.path.clone()),
+    /// Create the entry of a file, or anything else which is not a
+    /// directory, from its metadata.
+    pub(crate) fn new_file(path: &Path, metadata: &Metadata) -> Self {
+        Self {
+            name: extract_file_name(path),
+            path: path.to_path_buf(),
+            sizes: EntrySize::new(path, metadata),
+            dir_size: None,
+            descendants_count: 0,
+            entry_type: EntryType::File,
+            metadata: EntryMetadata::from(metadata),
+            mount_point: false,
+            incomplete: false,
+            change: None,
+        }
+    }
+
+    /// Turn the entry into a mount point which is not traversed and hence
+    /// does not contribute to the sizes.
+    pub(crate) fn into_mount_point(self) -> Self {
+        Self {
+            sizes: EntrySize::default(),
+            dir_size: Some(EntrySize::default()),
+            mount_point: true,
+            ..self
+        }
+    }
+
+    /// Delete the entry from the file system. Returns the trashed entry when
+    /// it was moved to the trash.
+    pub(crate) fn delete_entry(
+        &self,
+        deletion_mode: DeletionMode,
+    ) -> std::io::Result<Option<TrashedEntry>> {
+        match (deletion_mode, self.entry_type) {
+            (DeletionMode::Trash, _) => trash::move_to_trash(&self.path).map(Some),
+            (DeletionMode::Permanent, EntryType::Directory) => {
+                std::fs::remove_dir_all(self.path.clone()).map(|()| None)
+            }
+            (DeletionMode::Permanent, EntryType::File) => {
+                std::fs::remove_file(self.path.clone()).map(|()| None)
+            }
         }
     }
 }
@@ -139,25 +242,60 @@ fn extract_mode(metadata: &Metadata) -> Mode {
 }
 
 #[cfg(not(any(unix, windows)))]
-pub fn extract_mode(_metadata: &Metadata) -> Mode {
+fn extract_mode(_metadata: &Metadata) -> Mode {
     Mode::Unknown
 }
 
 // Traits implementations
 
+impl From<&Metadata> for EntryMetadata {
+    #[cfg(unix)]
+    fn from(metadata: &Metadata) -> Self {
+        use std::os::unix::fs::MetadataExt;
+        Self {
+            mode: extract_mode(metadata),
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+            device: metadata.dev(),
+            inode: metadata.ino(),
+            links: metadata.nlink(),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn from(metadata: &Metadata) -> Self {
+        Self {
+            mode: extract_mode(metadata),
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+            device: 0,
+            inode: 0,
+            links: 1,
+        }
+    }
+}
+
 impl Display for EntryNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:<20} • {}", self.name, self.sizes.apparent_size)
+        write!(f, "{:<20} • {}", self.name, self.sizes.apparent_size)?;
+        if self.mount_point {
+            write!(f, " (mount point)")?;
+        }
+        if self.incomplete {
+            write!(f, " (incomplete)")?;
+        }
+        if let Some(change) = self.change {
+            write!(f, " ({:+})", change.delta(SizeMetric::Apparent))?;
+        }
+        Ok(())
     }
 }
 
 impl TryFrom<&jwalk::DirEntry<CustomJWalkClientState>> for EntryNode {
-    type Error = &'static str;
+    type Error = jwalk::Error;
 
     fn try_from(value: &jwalk::DirEntry<CustomJWalkClientState>) -> Result<Self, Self::Error> {
-        let Ok(metadata) = value.metadata() else {
-            return Err("Error getting metadata from DirEntry");
-        };
+        let metadata = value.metadata()?;
         let name = value.file_name().to_string_lossy().to_string();
         let entry_type = Self::extract_entry_type(value);
 
@@ -174,7 +312,10 @@ impl TryFrom<&jwalk::DirEntry<CustomJWalkClientState>> for EntryNode {
             dir_size,
             descendants_count: 0,
             entry_type,
-            metadata,
+            metadata: EntryMetadata::from(&metadata),
+            mount_point: false,
+            incomplete: false,
+            change: None,
         })
     }
 }
@@ -189,3 +330,32 @@ impl EntryNode {
         EntryType::File
     }
 }
+
+#[cfg(test)]
+impl EntryNode {
+    /// An entry which does not exist on the file system, with the given size
+    /// as both its apparent and its disk size.
+    pub(crate) fn for_test(path: &str, entry_type: EntryType, size: u64) -> Self {
+        let path = PathBuf::from(path);
+        let sizes = EntrySize {
+            apparent_size: size,
+            disk_size: size,
+            inodes: 1,
+        };
+        Self {
+            name: extract_file_name(&path),
+            path,
+            sizes,
+            dir_size: matches!(entry_type, EntryType::Directory).then_some(sizes),
+            descendants_count: 0,
+            entry_type,
+            metadata: EntryMetadata {
+                links: 1,
+                ..EntryMetadata::default()
+            },
+            mount_point: false,
+            incomplete: false,
+            change: None,
+        }
+    }
+}
@@ -1,11 +1,41 @@
 use filesize::PathExt;
+use serde::{Deserialize, Serialize};
 use std::ops::{Add, AddAssign, Sub, SubAssign};
 use std::path::Path;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
 pub struct EntrySize {
     pub apparent_size: u64,
     pub disk_size: u64,
+    /// Number of inodes, like `du --inodes`.
+    pub inodes: u64,
+}
+
+/// Which of the sizes of an entry is displayed and compared.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SizeMetric {
+    #[default]
+    Apparent,
+    Disk,
+    Inodes,
+}
+
+/// The sizes of an entry in two scans, `None` if the entry is missing from
+/// the scan.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SizeChange {
+    pub old: Option<EntrySize>,
+    pub new: Option<EntrySize>,
+}
+
+/// How an entry has changed between two scans.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Grown,
+    Shrunk,
+    Unchanged,
 }
 
 impl EntrySize {
@@ -13,6 +43,63 @@ impl EntrySize {
         Self {
             apparent_size: metadata.len(),
             disk_size: path.size_on_disk_fast(metadata).unwrap_or(0),
+            inodes: 1,
+        }
+    }
+
+    pub fn get(&self, metric: SizeMetric) -> u64 {
+        match metric {
+            SizeMetric::Apparent => self.apparent_size,
+            SizeMetric::Disk => self.disk_size,
+            SizeMetric::Inodes => self.inodes,
+        }
+    }
+}
+
+impl SizeMetric {
+    /// The metric following this one when cycling through the metrics.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Apparent => Self::Disk,
+            Self::Disk => Self::Inodes,
+            Self::Inodes => Self::Apparent,
+        }
+    }
+
+    /// The metric used where a size in bytes is needed, the apparent size
+    /// when counting inodes.
+    pub fn in_bytes(self) -> Self {
+        match self {
+            Self::Inodes => Self::Apparent,
+            metric => metric,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Apparent => "apparent size",
+            Self::Disk => "disk size",
+            Self::Inodes => "inodes",
+        }
+    }
+}
+
+impl SizeChange {
+    /// How much the entry has grown, negative if it has shrunk.
+    pub fn delta(&self, metric: SizeMetric) -> i128 {
+        let size = |size: Option<EntrySize>| i128::from(size.unwrap_or_default().get(metric));
+        size(self.new) - size(self.old)
+    }
+
+    pub fn kind(&self, metric: SizeMetric) -> ChangeKind {
+        match (self.old, self.new) {
+            (None, _) => ChangeKind::Added,
+            (_, None) => ChangeKind::Removed,
+            _ => match self.delta(metric).signum() {
+                1 => ChangeKind::Grown,
+                -1 => ChangeKind::Shrunk,
+                _ => ChangeKind::Unchanged,
+            },
         }
     }
 }
@@ -24,6 +111,7 @@ impl Add for EntrySize {
         Self {
             apparent_size: self.apparent_size + other.apparent_size,
             disk_size: self.disk_size + other.disk_size,
+            inodes: self.inodes + other.inodes,
         }
     }
 }
@@ -35,24 +123,19 @@ impl Sub for EntrySize {
         Self {
             apparent_size: self.apparent_size - other.apparent_size,
             disk_size: self.disk_size - other.disk_size,
+            inodes: self.inodes - other.inodes,
         }
     }
 }
 
 impl AddAssign for EntrySize {
     fn add_assign(&mut self, rhs: Self) {
-        *self = EntrySize {
-            apparent_size: self.apparent_size + rhs.apparent_size,
-            disk_size: self.disk_size + rhs.disk_size,
-        };
+        *self = *self + rhs;
     }
 }
 
 impl SubAssign for EntrySize {
     fn sub_assign(&mut self, rhs: Self) {
-        *self = EntrySize {
-            apparent_size: self.apparent_size - rhs.apparent_size,
-            disk_size: self.disk_size - rhs.disk_size,
-        };
+        *self = *self - rhs;
     }
 }
@@ -0,0 +1,91 @@
+use std::{
+    io::Write,
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{bail, Result};
+use ref_tree::Node;
+use serde::{ser::SerializeMap, ser::SerializeSeq, Serialize, Serializer};
+
+use super::entry_node::{EntryNode, EntryType};
+
+/// Formats the scanned tree can be exported to.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ExportFormat {
+    Json,
+    Ncdu,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "json" => Ok(Self::Json),
+            "ncdu" => Ok(Self::Ncdu),
+            _ => bail!("Unknown export format '{format}', expected 'json' or 'ncdu'"),
+        }
+    }
+}
+
+/// Write the tree under the given root as JSON.
+///
+/// The nodes are serialized while walking the tree, so no copy of the tree
+/// is made.
+pub(crate) fn export_json(
+    root: &Arc<RwLock<Node<EntryNode>>>,
+    mut writer: impl Write,
+) -> Result<()> {
+    serde_json::to_writer(&mut writer, &JsonNode(root))?;
+    writer.flush()?;
+    Ok(())
+}
+
+struct JsonNode<'a>(&'a Arc<RwLock<Node<EntryNode>>>);
+
+struct JsonChildren(Vec<Arc<RwLock<Node<EntryNode>>>>);
+
+impl Serialize for JsonNode<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node = self.0.read().expect("Failed to read node while exporting");
+        let entry = &node.data;
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("name", &entry.name)?;
+        // Paths which are not valid UTF-8 cannot be written as JSON strings.
+        map.serialize_entry("path", &entry.path.to_string_lossy())?;
+        map.serialize_entry("apparent_size", &entry.sizes.apparent_size)?;
+        map.serialize_entry("disk_size", &entry.sizes.disk_size)?;
+        map.serialize_entry("inodes", &entry.sizes.inodes)?;
+        map.serialize_entry(
+            "entry_type",
+            match entry.entry_type {
+                EntryType::Directory => "directory",
+                EntryType::File => "file",
+            },
+        )?;
+        map.serialize_entry("mtime", &entry.metadata.modified_timestamp())?;
+        map.serialize_entry("mode", &entry.metadata.mode.bits())?;
+        if entry.mount_point {
+            map.serialize_entry("mount_point", &true)?;
+        }
+        if let EntryType::Directory = entry.entry_type {
+            let children = node.get_children();
+            // Do not hold the lock while serializing the whole subtree.
+            std::mem::drop(node);
+            map.serialize_entry("children", &JsonChildren(children))?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for JsonChildren {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for child in &self.0 {
+            seq.serialize_element(&JsonNode(child))?;
+        }
+        seq.end()
+    }
+}
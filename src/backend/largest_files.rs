@@ -0,0 +1,114 @@
+//! Keeping track of the largest files in the whole tree.
+
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use super::entry_size::{EntrySize, SizeMetric};
+
+/// How many of the largest files are kept by default.
+pub(crate) const DEFAULT_LARGEST_FILES_COUNT: usize = 100;
+
+pub(crate) type SharedLargestFiles = Arc<Mutex<LargestFiles>>;
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Candidate {
+    size: u64,
+    path: PathBuf,
+}
+
+/// The paths of the largest files seen so far, one bounded heap per size in
+/// bytes. The smallest kept file is on the top of each heap, so it can be
+/// replaced by a larger one quickly.
+#[derive(Debug)]
+pub(crate) struct LargestFiles {
+    capacity: usize,
+    apparent: BinaryHeap<Reverse<Candidate>>,
+    disk: BinaryHeap<Reverse<Candidate>>,
+    /// Whether the tree has changed in a way the heaps cannot follow, e.g.,
+    /// entries have been deleted.
+    stale: bool,
+}
+
+impl Default for LargestFiles {
+    fn default() -> Self {
+        Self::new(DEFAULT_LARGEST_FILES_COUNT)
+    }
+}
+
+impl LargestFiles {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            apparent: BinaryHeap::with_capacity(capacity + 1),
+            disk: BinaryHeap::with_capacity(capacity + 1),
+            stale: false,
+        }
+    }
+
+    /// Keep the file if it is among the largest ones.
+    pub(crate) fn offer(&mut self, path: &Path, sizes: EntrySize) {
+        Self::offer_to(&mut self.apparent, self.capacity, path, sizes.apparent_size);
+        Self::offer_to(&mut self.disk, self.capacity, path, sizes.disk_size);
+    }
+
+    fn offer_to(
+        heap: &mut BinaryHeap<Reverse<Candidate>>,
+        capacity: usize,
+        path: &Path,
+        size: u64,
+    ) {
+        if capacity == 0 {
+            return;
+        }
+        if heap.len() == capacity {
+            match heap.peek() {
+                Some(Reverse(smallest)) if smallest.size < size => {
+                    heap.pop();
+                }
+                _ => return,
+            }
+        }
+        heap.push(Reverse(Candidate {
+            size,
+            path: path.to_path_buf(),
+        }));
+    }
+
+    /// The paths of the largest files, the largest first.
+    pub(crate) fn paths(&self, size_metric: SizeMetric) -> Vec<PathBuf> {
+        let heap = match size_metric.in_bytes() {
+            SizeMetric::Disk => &self.disk,
+            _ => &self.apparent,
+        };
+        let mut candidates: Vec<&Candidate> =
+            heap.iter().map(|Reverse(candidate)| candidate).collect();
+        candidates.sort_unstable_by(|a, b| b.cmp(a));
+        candidates
+            .into_iter()
+            .map(|candidate| candidate.path.clone())
+            .collect()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.apparent.is_empty()
+    }
+
+    pub(crate) fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    pub(crate) fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Forget all files, so that the heaps can be filled again.
+    pub(crate) fn clear(&mut self) {
+        self.apparent.clear();
+        self.disk.clear();
+        self.stale = false;
+    }
+}
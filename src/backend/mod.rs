@@ -1,5 +1,19 @@
+pub(crate) mod deletion;
+pub(crate) mod diff;
 pub(crate) mod entry_node;
 pub(crate) mod entry_size;
+pub(crate) mod export;
+pub(crate) mod largest_files;
+pub(crate) mod name_pattern;
+pub(crate) mod ncdu;
+pub(crate) mod search;
+pub(crate) mod snapshot;
+pub(crate) mod sort_mode;
+pub(crate) mod trash;
+pub(crate) mod traversal_options;
 pub(crate) mod tree_walk_state;
 
 pub(crate) mod disko_tree;
+pub(crate) mod duplicates;
+#[cfg(target_os = "linux")]
+pub(crate) mod watcher;
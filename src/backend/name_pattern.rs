@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+
+/// How a pattern is matched against entry names.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PatternKind {
+    #[default]
+    Substring,
+    Glob,
+    Regex,
+}
+
+impl PatternKind {
+    /// The kind following this one when cycling through the kinds.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Substring => Self::Glob,
+            Self::Glob => Self::Regex,
+            Self::Regex => Self::Substring,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Substring => "substring",
+            Self::Glob => "glob",
+            Self::Regex => "regex",
+        }
+    }
+}
+
+/// A compiled pattern matching entry names.
+///
+/// Matching ignores case unless the pattern contains an uppercase letter.
+#[derive(Clone, Debug)]
+pub enum NamePattern {
+    Substring { needle: String, ignore_case: bool },
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    pub fn new(kind: PatternKind, pattern: &str) -> Result<Self> {
+        let ignore_case = !pattern.chars().any(char::is_uppercase);
+        Ok(match kind {
+            PatternKind::Substring => Self::Substring {
+                needle: if ignore_case {
+                    pattern.to_lowercase()
+                } else {
+                    pattern.to_string()
+                },
+                ignore_case,
+            },
+            PatternKind::Glob => Self::Glob(
+                GlobBuilder::new(pattern)
+                    .case_insensitive(ignore_case)
+                    .literal_separator(true)
+                    .build()
+                    .with_context(|| format!("Invalid glob '{pattern}'"))?
+                    .compile_matcher(),
+            ),
+            PatternKind::Regex => Self::Regex(
+                RegexBuilder::new(pattern)
+                    .case_insensitive(ignore_case)
+                    .build()
+                    .with_context(|| format!("Invalid regex '{pattern}'"))?,
+            ),
+        })
+    }
+
+    pub fn is_match(&self, name: &str) -> bool {
+        match self {
+            Self::Substring {
+                needle,
+                ignore_case: true,
+            } => name.to_lowercase().contains(needle.as_str()),
+            Self::Substring { needle, .. } => name.contains(needle.as_str()),
+            Self::Glob(glob) => glob.is_match(name),
+            Self::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
@@ -0,0 +1,556 @@
+//! Reading and writing the JSON export format of
+//! [ncdu](https://dev.yorhel.nl/ncdu/jsonfmt).
+
+use std::{
+    collections::HashSet,
+    fmt,
+    io::{Read, Write},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use ref_tree::{Node, Tree};
+use serde::{
+    de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::{
+    entry_node::{EntryMetadata, EntryNode, EntryType, Mode},
+    entry_size::EntrySize,
+};
+
+const MAJOR_VERSION: u64 = 1;
+const MINOR_VERSION: u64 = 2;
+
+/// Write the tree under the given root as an ncdu export.
+pub(crate) fn export(root: &Arc<RwLock<Node<EntryNode>>>, mut writer: impl Write) -> Result<()> {
+    let header = Header {
+        progname: env!("CARGO_PKG_NAME"),
+        progver: env!("CARGO_PKG_VERSION"),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    };
+    serde_json::to_writer(
+        &mut writer,
+        &(
+            MAJOR_VERSION,
+            MINOR_VERSION,
+            header,
+            ExportedEntry {
+                node: root,
+                is_root: true,
+            },
+        ),
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Build a tree from an ncdu export.
+///
+/// Returns the tree together with the number of entries the export marks
+/// as excluded by a pattern.
+pub(crate) fn import(reader: impl Read) -> Result<(Tree<EntryNode>, usize)> {
+    let mut state = ImportState::default();
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    // Every directory nests one level deeper, real file systems go deeper
+    // than the recursion limit of serde_json. The stack grows on the heap
+    // instead.
+    deserializer.disable_recursion_limit();
+    ExportSeed(&mut state).deserialize(serde_stacker::Deserializer::new(&mut deserializer))?;
+    deserializer.end()?;
+    Ok((state.tree, state.excluded_count))
+}
+
+// Export
+
+#[derive(Serialize)]
+struct Header {
+    progname: &'static str,
+    progver: &'static str,
+    timestamp: u64,
+}
+
+/// Directories are written as arrays of their info followed by their
+/// children, files as a plain info object.
+struct ExportedEntry<'a> {
+    node: &'a Arc<RwLock<Node<EntryNode>>>,
+    is_root: bool,
+}
+
+struct ExportedInfo<'a> {
+    entry: &'a EntryNode,
+    is_root: bool,
+    /// Whether the entry is where the sizes became incomplete, its ancestors
+    /// are marked by the importer.
+    read_error: bool,
+}
+
+impl Serialize for ExportedEntry<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node = self
+            .node
+            .read()
+            .expect("Failed to read node while exporting");
+        let children = node.get_children();
+        let info = ExportedInfo {
+            entry: &node.data,
+            is_root: self.is_root,
+            read_error: node.data.incomplete
+                && !children.iter().any(|child| {
+                    child
+                        .read()
+                        .expect("Failed to read node while exporting")
+                        .data
+                        .incomplete
+                }),
+        };
+
+        if !matches!(node.data.entry_type, EntryType::Directory) || node.data.mount_point {
+            return info.serialize(serializer);
+        }
+
+        let mut seq = serializer.serialize_seq(Some(children.len() + 1))?;
+        seq.serialize_element(&info)?;
+        // Do not hold the lock while serializing the whole subtree.
+        std::mem::drop(node);
+        for child in &children {
+            seq.serialize_element(&ExportedEntry {
+                node: child,
+                is_root: false,
+            })?;
+        }
+        seq.end()
+    }
+}
+
+impl Serialize for ExportedInfo<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let entry = self.entry;
+        // Directories only report their own size, ncdu sums the children.
+        let size = entry.dir_size.unwrap_or(entry.sizes);
+
+        let mut map = serializer.serialize_map(None)?;
+        if self.is_root {
+            map.serialize_entry("name", &entry.path.to_string_lossy())?;
+        } else {
+            map.serialize_entry("name", &entry.name)?;
+        }
+        map.serialize_entry("asize", &size.apparent_size)?;
+        map.serialize_entry("dsize", &size.disk_size)?;
+        if self.is_root {
+            map.serialize_entry("dev", &entry.metadata.device)?;
+        }
+        map.serialize_entry("ino", &entry.metadata.inode)?;
+        // Directories always have several links, only other entries can be
+        // hard linked.
+        if entry.metadata.links > 1 && !matches!(entry.entry_type, EntryType::Directory) {
+            map.serialize_entry("hlnkc", &true)?;
+            map.serialize_entry("nlink", &entry.metadata.links)?;
+        }
+        if let Some(mode) = entry.metadata.mode.bits() {
+            map.serialize_entry("mode", &mode)?;
+        }
+        if let Some(mtime) = entry.metadata.modified_timestamp() {
+            map.serialize_entry("mtime", &mtime)?;
+        }
+        if self.read_error {
+            map.serialize_entry("read_error", &true)?;
+        }
+        if entry.mount_point {
+            map.serialize_entry("excluded", "otherfs")?;
+        }
+        map.end()
+    }
+}
+
+// Import
+
+#[derive(Default)]
+struct ImportState {
+    tree: Tree<EntryNode>,
+    excluded_count: usize,
+    seen_hard_links: HashSet<(u64, u64)>,
+}
+
+/// The info object describing every entry in the export.
+#[derive(Deserialize)]
+struct ImportedInfo {
+    name: String,
+    #[serde(default)]
+    asize: u64,
+    #[serde(default)]
+    dsize: u64,
+    #[serde(default)]
+    dev: u64,
+    #[serde(default)]
+    ino: u64,
+    #[serde(default)]
+    nlink: Option<u64>,
+    #[serde(default)]
+    hlnkc: bool,
+    #[serde(default)]
+    mode: Option<u32>,
+    #[serde(default)]
+    mtime: Option<u64>,
+    #[serde(default)]
+    read_error: bool,
+    #[serde(default)]
+    excluded: Option<String>,
+}
+
+impl ImportedInfo {
+    fn into_entry_node(
+        self,
+        parent: Option<&Arc<RwLock<Node<EntryNode>>>>,
+        is_dir: bool,
+    ) -> EntryNode {
+        let size = EntrySize {
+            apparent_size: self.asize,
+            disk_size: self.dsize,
+            inodes: 1,
+        };
+        let (name, path) = match parent {
+            Some(parent) => {
+                let parent = parent
+                    .read()
+                    .expect("Failed to read parent while importing");
+                let path = parent.data.path.join(&self.name);
+                (self.name, path)
+            }
+            // The root entry carries the full path as its name.
+            None => {
+                let path = PathBuf::from(&self.name);
+                let name = path
+                    .file_name()
+                    .map_or(self.name, |name| name.to_string_lossy().to_string());
+                (name, path)
+            }
+        };
+
+        EntryNode {
+            name,
+            path,
+            sizes: size,
+            dir_size: is_dir.then_some(size),
+            descendants_count: 0,
+            entry_type: if is_dir {
+                EntryType::Directory
+            } else {
+                EntryType::File
+            },
+            metadata: EntryMetadata {
+                mode: self.mode.map_or(Mode::Unknown, Mode::Permissions),
+                modified: self
+                    .mtime
+                    .map(|mtime| UNIX_EPOCH + Duration::from_secs(mtime)),
+                accessed: None,
+                device: self.dev,
+                inode: self.ino,
+                links: self.nlink.unwrap_or(1),
+            },
+            mount_point: false,
+            incomplete: self.read_error,
+            change: None,
+        }
+    }
+}
+
+/// Deserializes the whole export: `[major, minor, header, root]`.
+struct ExportSeed<'a>(&'a mut ImportState);
+
+impl<'de> DeserializeSeed<'de> for ExportSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for ExportSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an ncdu export array")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        let major: u64 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        if major != MAJOR_VERSION {
+            return Err(de::Error::custom(format!(
+                "unsupported ncdu export version {major}"
+            )));
+        }
+        seq.next_element::<IgnoredAny>()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        seq.next_element::<IgnoredAny>()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        seq.next_element_seed(EntrySeed {
+            state: self.0,
+            parent: None,
+        })?
+        .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+        // Ignore any future additions.
+        while seq.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(())
+    }
+}
+
+/// Deserializes a single entry, i.e., a directory array or a file object,
+/// attaches it to the tree and returns its total size, the number of
+/// attached entries including itself and whether its size is incomplete.
+struct EntrySeed<'a> {
+    state: &'a mut ImportState,
+    parent: Option<&'a Arc<RwLock<Node<EntryNode>>>>,
+}
+
+impl EntrySeed<'_> {
+    fn attach(&mut self, entry: EntryNode) -> Arc<RwLock<Node<EntryNode>>> {
+        match self.parent {
+            Some(parent) => Tree::attach_child(parent, entry),
+            None => self
+                .state
+                .tree
+                .create_node_and_set_root(entry)
+                .expect("The imported tree should not have a root yet"),
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for EntrySeed<'_> {
+    type Value = (EntrySize, usize, bool);
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<(EntrySize, usize, bool), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for EntrySeed<'_> {
+    type Value = (EntrySize, usize, bool);
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an ncdu directory array or file object")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(
+        mut self,
+        mut seq: A,
+    ) -> Result<(EntrySize, usize, bool), A::Error> {
+        let info: ImportedInfo = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let node = self.attach(info.into_entry_node(self.parent, true));
+
+        let (mut size, mut incomplete) = {
+            let node = node.read().expect("Failed to read imported directory");
+            (node.data.sizes, node.data.incomplete)
+        };
+        let mut descendants_count = 0;
+        while let Some((child_size, child_count, child_incomplete)) =
+            seq.next_element_seed(EntrySeed {
+                state: &mut *self.state,
+                parent: Some(&node),
+            })?
+        {
+            size += child_size;
+            descendants_count += child_count;
+            incomplete |= child_incomplete;
+        }
+
+        let mut node = node.write().expect("Failed to write imported directory");
+        node.data.sizes = size;
+        node.data.descendants_count = descendants_count;
+        node.data.incomplete = incomplete;
+        Ok((size, descendants_count + 1, incomplete))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(
+        mut self,
+        map: A,
+    ) -> Result<(EntrySize, usize, bool), A::Error> {
+        let info = ImportedInfo::deserialize(de::value::MapAccessDeserializer::new(map))?;
+
+        // Mount points are the only directories written as objects.
+        let is_hard_link = info.hlnkc && info.excluded.is_none();
+        let mut entry = match info.excluded.as_deref() {
+            None => info.into_entry_node(self.parent, false),
+            // Directories on other file systems.
+            Some("otherfs" | "kernfs") => {
+                info.into_entry_node(self.parent, true).into_mount_point()
+            }
+            // Excluded by a pattern or for any other reason.
+            Some(_) => {
+                self.state.excluded_count += 1;
+                return Ok((EntrySize::default(), 0, false));
+            }
+        };
+
+        // Count every hard linked inode only once. Our own exports already
+        // leave out the size of all links but one, which may come later.
+        let counted = entry.sizes.apparent_size > 0 || entry.sizes.disk_size > 0;
+        if is_hard_link
+            && counted
+            && !self
+                .state
+                .seen_hard_links
+                .insert((entry.metadata.device, entry.metadata.inode))
+        {
+            entry.sizes = EntrySize::default();
+        }
+
+        let (size, incomplete) = (entry.sizes, entry.incomplete);
+        self.attach(entry);
+        Ok((size, 1, incomplete))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hard_link(path: &str, size: u64) -> EntryNode {
+        let mut entry = EntryNode::for_test(path, EntryType::File, size);
+        entry.metadata.device = 1;
+        entry.metadata.inode = 42;
+        entry.metadata.links = 2;
+        entry
+    }
+
+    /// Export the tree and import it again.
+    fn round_trip(tree: &Tree<EntryNode>) -> Tree<EntryNode> {
+        let mut exported = Vec::new();
+        export(&tree.get_root().unwrap(), &mut exported).unwrap();
+        import(exported.as_slice()).unwrap().0
+    }
+
+    fn find(tree: &Tree<EntryNode>, name: &str) -> EntryNode {
+        let mut stack = vec![tree.get_root().unwrap()];
+        while let Some(node) = stack.pop() {
+            let node = node.read().unwrap();
+            if node.data.name == name {
+                return node.data.clone();
+            }
+            stack.extend(node.get_children());
+        }
+        panic!("{name} is not in the tree");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut tree = Tree::new();
+        let mut root_entry = EntryNode::for_test("/r", EntryType::Directory, 4096);
+        root_entry.incomplete = true;
+        let root = tree.create_node_and_set_root(root_entry).unwrap();
+        Tree::attach_child(&root, hard_link("/r/a", 100));
+        // The scan counts the size of hard linked files only once.
+        let mut second_link = hard_link("/r/b", 100);
+        second_link.sizes = EntrySize::default();
+        Tree::attach_child(&root, second_link);
+        let mut unreadable = EntryNode::for_test("/r/bad", EntryType::Directory, 4096);
+        unreadable.incomplete = true;
+        let unreadable = Tree::attach_child(&root, unreadable);
+        Tree::attach_child(
+            &unreadable,
+            EntryNode::for_test("/r/bad/c", EntryType::File, 5),
+        );
+
+        let imported = round_trip(&tree);
+        let root = find(&imported, "r");
+        assert_eq!(root.path, PathBuf::from("/r"));
+        assert_eq!(root.sizes.apparent_size, 4096 + 100 + 4096 + 5);
+        assert_eq!(root.sizes.disk_size, 4096 + 100 + 4096 + 5);
+        assert_eq!(root.descendants_count, 4);
+        assert!(root.incomplete);
+
+        let (a, b) = (find(&imported, "a"), find(&imported, "b"));
+        assert_eq!(a.metadata.links, 2);
+        assert_eq!(b.metadata.links, 2);
+        assert_eq!(a.sizes.apparent_size + b.sizes.apparent_size, 100);
+        assert!(!a.incomplete);
+
+        assert!(find(&imported, "bad").incomplete);
+        assert!(!find(&imported, "c").incomplete);
+    }
+
+    #[test]
+    fn test_round_trip_nested_directories() {
+        let directory = |path: &str, inode: u64, links: u64| {
+            let mut entry = EntryNode::for_test(path, EntryType::Directory, 4096);
+            entry.metadata.device = 1;
+            entry.metadata.inode = inode;
+            entry.metadata.links = links;
+            entry
+        };
+        let mut tree = Tree::new();
+        let root = tree
+            .create_node_and_set_root(directory("/r", 1, 4))
+            .unwrap();
+        let outer = Tree::attach_child(&root, directory("/r/outer", 2, 3));
+        let inner = Tree::attach_child(&outer, directory("/r/outer/inner", 3, 2));
+        Tree::attach_child(&inner, hard_link("/r/outer/inner/a", 100));
+        Tree::attach_child(&root, directory("/r/empty", 4, 2));
+        let mut mount = directory("/r/mount", 5, 2).into_mount_point();
+        mount.metadata.device = 2;
+        Tree::attach_child(&root, mount);
+
+        let mut exported = Vec::new();
+        export(&tree.get_root().unwrap(), &mut exported).unwrap();
+        let text = String::from_utf8(exported).unwrap();
+        assert_eq!(text.matches("hlnkc").count(), 1, "{text}");
+
+        let imported = round_trip(&tree);
+        let root = find(&imported, "r");
+        assert_eq!(root.sizes.apparent_size, 4 * 4096 + 100);
+        assert_eq!(root.descendants_count, 5);
+        assert_eq!(find(&imported, "outer").sizes.apparent_size, 2 * 4096 + 100);
+        assert_eq!(find(&imported, "inner").sizes.apparent_size, 4096 + 100);
+        assert_eq!(find(&imported, "empty").sizes.apparent_size, 4096);
+        assert_eq!(find(&imported, "a").metadata.links, 2);
+        assert!(find(&imported, "mount").mount_point);
+    }
+
+    #[test]
+    fn test_import_counts_hard_links_once() {
+        let export = r#"[1, 2, {}, [{"name": "/r", "asize": 10},
+            {"name": "a", "asize": 100, "ino": 7, "hlnkc": true, "nlink": 2},
+            {"name": "b", "asize": 100, "ino": 7, "hlnkc": true, "nlink": 2}]]"#;
+        let (tree, _) = import(export.as_bytes()).unwrap();
+        let root = tree.get_root().unwrap();
+        assert_eq!(root.read().unwrap().data.sizes.apparent_size, 110);
+    }
+
+    #[test]
+    fn test_import_read_error() {
+        let export = r#"[1, 2, {}, [{"name": "/r"}, [{"name": "d", "read_error": true}],
+            {"name": "f"}]]"#;
+        let (tree, _) = import(export.as_bytes()).unwrap();
+        assert!(find(&tree, "r").incomplete);
+        assert!(find(&tree, "d").incomplete);
+        assert!(!find(&tree, "f").incomplete);
+    }
+
+    #[test]
+    fn test_import_deep_export() {
+        let depth = 2000;
+        let mut export = String::from(r#"[1, 2, {}, "#);
+        for index in 0..depth {
+            export.push_str(&format!(r#"[{{"name": "{index}"}}, "#));
+        }
+        export.push_str(r#"{"name": "f", "asize": 1}"#);
+        export.push_str(&"]".repeat(depth + 1));
+        let (tree, _) = import(export.as_bytes()).unwrap();
+        let root = tree.get_root().unwrap();
+        assert_eq!(root.read().unwrap().data.descendants_count, depth);
+    }
+}
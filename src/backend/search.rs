@@ -0,0 +1,340 @@
+//! Searching the whole scanned tree by name, size and modification time.
+
+use std::time::{Duration, SystemTime};
+
+use anyhow::{bail, Context, Result};
+use byte_unit::Byte;
+use chrono::{Local, NaiveDate, TimeZone};
+
+use super::{
+    entry_node::EntryNode,
+    entry_size::SizeMetric,
+    name_pattern::{NamePattern, PatternKind},
+};
+
+const SIZE_PREFIX: &str = "size:";
+const MTIME_PREFIX: &str = "mtime:";
+
+/// What the entries are searched for, all of the given criteria have to
+/// match.
+///
+/// A query consists of whitespace separated terms:
+/// - `size:>100M`, `size:<1G` or `size:10M..1G` for the size in bytes,
+/// - `mtime:<7d` for entries modified within the last 7 days, `mtime:>1y`
+///   for entries modified more than a year ago, `mtime:>2024-01-01` or
+///   `mtime:2024-01-01..2024-06-30` for dates, ages are given in `h`, `d`,
+///   `w` or `y`,
+/// - everything else is the name pattern.
+#[derive(Debug)]
+pub struct SearchQuery {
+    name: Option<NamePattern>,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str, kind: PatternKind) -> Result<Self> {
+        let mut query = Self {
+            name: None,
+            min_size: None,
+            max_size: None,
+            modified_after: None,
+            modified_before: None,
+        };
+        let mut name = Vec::new();
+
+        for term in input.split_whitespace() {
+            if let Some(range) = term.strip_prefix(SIZE_PREFIX) {
+                query.parse_size(range)?;
+            } else if let Some(range) = term.strip_prefix(MTIME_PREFIX) {
+                query.parse_mtime(range)?;
+            } else {
+                name.push(term);
+            }
+        }
+
+        if !name.is_empty() {
+            query.name = Some(NamePattern::new(kind, &name.join(" "))?);
+        }
+        if query.name.is_none()
+            && query.min_size.is_none()
+            && query.max_size.is_none()
+            && query.modified_after.is_none()
+            && query.modified_before.is_none()
+        {
+            bail!("Empty search");
+        }
+        Ok(query)
+    }
+
+    pub(crate) fn matches(&self, entry: &EntryNode, size_metric: SizeMetric) -> bool {
+        if let Some(name) = &self.name {
+            if !name.is_match(&entry.name) {
+                return false;
+            }
+        }
+
+        let size = entry.sizes.get(size_metric.in_bytes());
+        if self.min_size.is_some_and(|min| size < min)
+            || self.max_size.is_some_and(|max| size > max)
+        {
+            return false;
+        }
+
+        if self.modified_after.is_none() && self.modified_before.is_none() {
+            return true;
+        }
+        let Some(modified) = entry.metadata.modified else {
+            return false;
+        };
+        !(self.modified_after.is_some_and(|after| modified < after)
+            || self.modified_before.is_some_and(|before| modified > before))
+    }
+
+    fn parse_size(&mut self, range: &str) -> Result<()> {
+        let parse = |size: &str| {
+            Byte::parse_str(size, true)
+                .map(|size| size.as_u64())
+                .with_context(|| format!("Invalid size '{size}'"))
+        };
+        if let Some(size) = range.strip_prefix('>') {
+            self.min_size = Some(parse(size)?.saturating_add(1));
+        } else if let Some(size) = range.strip_prefix('<') {
+            let max_size = parse(size)?.checked_sub(1);
+            self.max_size = Some(max_size.context("No size is smaller than 0")?);
+        } else if let Some((min, max)) = range.split_once("..") {
+            self.min_size = Some(parse(min)?);
+            self.max_size = Some(parse(max)?);
+        } else {
+            let size = parse(range)?;
+            self.min_size = Some(size);
+            self.max_size = Some(size);
+        }
+        Ok(())
+    }
+
+    fn parse_mtime(&mut self, range: &str) -> Result<()> {
+        // Ages count backwards, so being younger than an age means being
+        // modified after the corresponding time.
+        if let Some(time) = range.strip_prefix('<') {
+            match parse_time(time)? {
+                Time::Age(time) => self.modified_after = Some(time),
+                Time::Date(time) => self.modified_before = Some(time),
+            }
+        } else if let Some(time) = range.strip_prefix('>') {
+            match parse_time(time)? {
+                Time::Age(time) => self.modified_before = Some(time),
+                Time::Date(time) => self.modified_after = Some(time),
+            }
+        } else if let Some((start, end)) = range.split_once("..") {
+            let start = parse_time(start)?.into_inner();
+            let end = parse_time(end)?.into_inner();
+            self.modified_after = Some(start.min(end));
+            self.modified_before = Some(start.max(end));
+        } else {
+            bail!("Invalid modification time '{range}', expected <, > or a range");
+        }
+        Ok(())
+    }
+}
+
+enum Time {
+    /// The point in time lying the given age in the past.
+    Age(SystemTime),
+    Date(SystemTime),
+}
+
+impl Time {
+    fn into_inner(self) -> SystemTime {
+        match self {
+            Time::Age(time) | Time::Date(time) => time,
+        }
+    }
+}
+
+fn parse_time(time: &str) -> Result<Time> {
+    if let Ok(date) = NaiveDate::parse_from_str(time, "%Y-%m-%d") {
+        let midnight = date.and_hms_opt(0, 0, 0).expect("Midnight should be valid");
+        let date = Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .with_context(|| format!("Invalid date '{time}'"))?;
+        return Ok(Time::Date(date.into()));
+    }
+
+    let invalid = || format!("Invalid age or date '{time}'");
+    let (split, _) = time.char_indices().last().with_context(invalid)?;
+    let (count, unit) = time.split_at(split);
+    let count: u64 = count.parse().with_context(invalid)?;
+    let unit_seconds = match unit {
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => bail!(invalid()),
+    };
+    let age = Duration::from_secs(count.saturating_mul(unit_seconds));
+    Ok(Time::Age(
+        SystemTime::now()
+            .checked_sub(age)
+            .unwrap_or(SystemTime::UNIX_EPOCH),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::entry_node::EntryType;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn parse(input: &str) -> SearchQuery {
+        SearchQuery::parse(input, PatternKind::Substring).unwrap()
+    }
+
+    fn file(path: &str, size: u64) -> EntryNode {
+        EntryNode::for_test(path, EntryType::File, size)
+    }
+
+    fn modified_ago(age: Duration) -> EntryNode {
+        let mut entry = file("/a/file", 0);
+        entry.metadata.modified = Some(SystemTime::now() - age);
+        entry
+    }
+
+    fn local_date(year: i32, month: u32, day: u32) -> SystemTime {
+        let midnight = NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        Local
+            .from_local_datetime(&midnight)
+            .earliest()
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn test_parse_name() {
+        let query = parse("report  final");
+        assert!(query.min_size.is_none() && query.max_size.is_none());
+        assert!(query.matches(&file("/a/report final.pdf", 0), SizeMetric::Apparent));
+        assert!(!query.matches(&file("/a/report.pdf", 0), SizeMetric::Apparent));
+    }
+
+    #[test]
+    fn test_parse_empty() {
+        assert!(SearchQuery::parse("", PatternKind::Substring).is_err());
+        assert!(SearchQuery::parse("   ", PatternKind::Substring).is_err());
+    }
+
+    #[test]
+    fn test_parse_size_bounds_are_exclusive() {
+        let query = parse("size:>100");
+        assert_eq!(query.min_size, Some(101));
+        assert_eq!(query.max_size, None);
+
+        let query = parse("size:<100");
+        assert_eq!(query.min_size, None);
+        assert_eq!(query.max_size, Some(99));
+
+        assert!(SearchQuery::parse("size:<0", PatternKind::Substring).is_err());
+        assert_eq!(parse("size:<1").max_size, Some(0));
+    }
+
+    #[test]
+    fn test_parse_size_range_and_exact() {
+        let query = parse("size:10..20");
+        assert_eq!((query.min_size, query.max_size), (Some(10), Some(20)));
+
+        let query = parse("size:1KiB");
+        assert_eq!((query.min_size, query.max_size), (Some(1024), Some(1024)));
+    }
+
+    #[test]
+    fn test_parse_invalid_size() {
+        assert!(SearchQuery::parse("size:>big", PatternKind::Substring).is_err());
+        assert!(SearchQuery::parse("size:1..", PatternKind::Substring).is_err());
+    }
+
+    #[test]
+    fn test_matches_size() {
+        let query = parse("size:>100");
+        assert!(!query.matches(&file("/a/b", 100), SizeMetric::Apparent));
+        assert!(query.matches(&file("/a/b", 101), SizeMetric::Apparent));
+
+        let query = parse("size:<100");
+        assert!(query.matches(&file("/a/b", 99), SizeMetric::Apparent));
+        assert!(!query.matches(&file("/a/b", 100), SizeMetric::Apparent));
+    }
+
+    #[test]
+    fn test_mtime_age() {
+        // Younger than a week.
+        let query = parse("mtime:<7d");
+        assert!(query.modified_after.is_some() && query.modified_before.is_none());
+        assert!(query.matches(&modified_ago(DAY), SizeMetric::Apparent));
+        assert!(!query.matches(&modified_ago(8 * DAY), SizeMetric::Apparent));
+
+        // Older than a week.
+        let query = parse("mtime:>1w");
+        assert!(query.modified_after.is_none() && query.modified_before.is_some());
+        assert!(!query.matches(&modified_ago(DAY), SizeMetric::Apparent));
+        assert!(query.matches(&modified_ago(8 * DAY), SizeMetric::Apparent));
+    }
+
+    #[test]
+    fn test_mtime_date() {
+        let query = parse("mtime:<2024-01-01");
+        assert_eq!(query.modified_before, Some(local_date(2024, 1, 1)));
+        assert_eq!(query.modified_after, None);
+
+        let query = parse("mtime:>2024-01-01");
+        assert_eq!(query.modified_after, Some(local_date(2024, 1, 1)));
+        assert_eq!(query.modified_before, None);
+    }
+
+    #[test]
+    fn test_mtime_range_in_any_order() {
+        let expected = (Some(local_date(2024, 1, 1)), Some(local_date(2024, 6, 30)));
+        let query = parse("mtime:2024-01-01..2024-06-30");
+        assert_eq!((query.modified_after, query.modified_before), expected);
+        let query = parse("mtime:2024-06-30..2024-01-01");
+        assert_eq!((query.modified_after, query.modified_before), expected);
+    }
+
+    #[test]
+    fn test_mtime_requires_modification_time() {
+        let query = parse("mtime:<7d");
+        assert!(!query.matches(&file("/a/b", 0), SizeMetric::Apparent));
+    }
+
+    #[test]
+    fn test_parse_invalid_mtime() {
+        for input in [
+            "mtime:7d",
+            "mtime:<7x",
+            "mtime:<d",
+            "mtime:<",
+            "mtime:>2024-13-01",
+        ] {
+            assert!(
+                SearchQuery::parse(input, PatternKind::Substring).is_err(),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_combined_terms() {
+        let query = parse("log size:>10 mtime:<1d");
+        let mut entry = modified_ago(Duration::from_secs(60));
+        entry.name = "app.log".to_string();
+        entry.sizes.apparent_size = 11;
+        assert!(query.matches(&entry, SizeMetric::Apparent));
+        entry.sizes.apparent_size = 10;
+        assert!(!query.matches(&entry, SizeMetric::Apparent));
+    }
+}
@@ -0,0 +1,441 @@
+//! Saving the scanned tree to a binary snapshot file and loading it back.
+//!
+//! A snapshot starts with a magic number and a version, followed by a header
+//! describing the scan and by the entries of the tree in pre-order. Every
+//! entry carries only its own size, the sizes of the directories are summed
+//! again when the snapshot is loaded.
+
+use std::{
+    io::{self, Read, Write},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+use anyhow::{bail, Result};
+use bincode::Options;
+use ref_tree::{Node, Tree};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    entry_node::{EntryMetadata, EntryNode, EntryType},
+    entry_size::EntrySize,
+    traversal_options::TraversalOptions,
+    tree_walk_state::TraversalError,
+};
+
+const MAGIC: &[u8; 8] = b"DISKOSNP";
+const VERSION: u32 = 1;
+
+/// The error kinds kept in snapshots, any other kind is loaded as `Other`.
+const ERROR_KINDS: [io::ErrorKind; 5] = [
+    io::ErrorKind::Other,
+    io::ErrorKind::NotFound,
+    io::ErrorKind::PermissionDenied,
+    io::ErrorKind::Interrupted,
+    io::ErrorKind::InvalidData,
+];
+
+/// How the snapshot has been taken.
+#[derive(Clone, Debug)]
+pub struct SnapshotInfo {
+    /// The snapshot file.
+    pub path: PathBuf,
+    /// When the scan started.
+    pub scanned_at: SystemTime,
+    pub exclude_patterns: Vec<String>,
+    pub one_file_system: bool,
+}
+
+/// Everything in a snapshot besides the tree itself.
+pub(crate) struct SnapshotContents {
+    pub(crate) tree: Tree<EntryNode>,
+    pub(crate) info: SnapshotInfo,
+    pub(crate) excluded_count: usize,
+    pub(crate) errors: Vec<TraversalError>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    root: PathBuf,
+    scanned_at: SystemTime,
+    exclude_patterns: Vec<String>,
+    one_file_system: bool,
+    excluded_count: usize,
+    /// The unreadable entries with the indices of their error kinds.
+    errors: Vec<(PathBuf, u8)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotEntry {
+    name: String,
+    entry_type: EntryType,
+    /// The size of the entry itself, without its children.
+    size: EntrySize,
+    metadata: EntryMetadata,
+    mount_point: bool,
+    incomplete: bool,
+    /// How many of the following entries are the children of this one.
+    children: usize,
+}
+
+/// Write the tree under the given root, scanned with the given options, as a
+/// snapshot.
+pub(crate) fn save(
+    root: &Arc<RwLock<Node<EntryNode>>>,
+    scanned_at: SystemTime,
+    options: &TraversalOptions,
+    excluded_count: usize,
+    errors: &[TraversalError],
+    mut writer: impl Write,
+) -> Result<()> {
+    let header = Header {
+        root: root
+            .read()
+            .expect("Failed to read root while saving snapshot")
+            .data
+            .path
+            .clone(),
+        scanned_at,
+        exclude_patterns: options.exclude_patterns.clone(),
+        one_file_system: options.one_file_system,
+        excluded_count,
+        errors: errors
+            .iter()
+            .map(|error| {
+                let kind = ERROR_KINDS
+                    .iter()
+                    .position(|kind| *kind == error.kind)
+                    .unwrap_or(0);
+                (error.path.clone(), kind as u8)
+            })
+            .collect(),
+    };
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    bincode_options().serialize_into(&mut writer, &header)?;
+
+    let mut stack = vec![root.clone()];
+    while let Some(node) = stack.pop() {
+        let node = node
+            .read()
+            .expect("Failed to read node while saving snapshot");
+        let children = node.get_children();
+        let entry = &node.data;
+        bincode_options().serialize_into(
+            &mut writer,
+            &SnapshotEntry {
+                name: entry.name.clone(),
+                entry_type: entry.entry_type,
+                size: entry.dir_size.unwrap_or(entry.sizes),
+                metadata: entry.metadata,
+                mount_point: entry.mount_point,
+                incomplete: entry.incomplete,
+                children: children.len(),
+            },
+        )?;
+        // Reversed, so that the children are written in their order.
+        stack.extend(children.into_iter().rev());
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Build a tree from a snapshot. The path of the snapshot file is only
+/// recorded in the returned info.
+///
+/// No part of the snapshot can be longer than the whole snapshot, so the
+/// length of its file limits the allocations of a corrupt snapshot.
+pub(crate) fn load(path: PathBuf, mut reader: impl Read, length: u64) -> Result<SnapshotContents> {
+    let options = bincode_options().with_limit(length);
+    let mut magic = [0; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        bail!("Not a disko snapshot");
+    }
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        bail!("Unsupported snapshot version {version}");
+    }
+    let header: Header = options.deserialize_from(&mut reader)?;
+
+    let mut tree = Tree::new();
+    let root_entry: SnapshotEntry = options.deserialize_from(&mut reader)?;
+    let root_children = root_entry.children;
+    let root = tree
+        .create_node_and_set_root(root_entry.into_entry_node(header.root.clone()))
+        .expect("The loaded tree should not have a root yet");
+
+    // The directories whose children are being read, with the number of
+    // children left to read.
+    let mut stack = vec![(root, root_children)];
+    while let Some((directory, children_left)) = stack.last_mut() {
+        if *children_left == 0 {
+            let (directory, _) = stack.pop().expect("The stack should not be empty");
+            let (size, descendants_count) = {
+                let directory = directory.read().expect("Failed to read loaded directory");
+                (directory.data.sizes, directory.data.descendants_count)
+            };
+            if let Some((parent, _)) = stack.last() {
+                add_to_parent(parent, size, descendants_count + 1);
+            }
+            continue;
+        }
+        *children_left -= 1;
+        let directory = directory.clone();
+
+        let entry: SnapshotEntry = options.deserialize_from(&mut reader)?;
+        let children = entry.children;
+        let is_directory = matches!(entry.entry_type, EntryType::Directory);
+        let path = directory
+            .read()
+            .expect("Failed to read loaded directory")
+            .data
+            .path
+            .join(&entry.name);
+        let node = Tree::attach_child(&directory, entry.into_entry_node(path));
+        if is_directory {
+            stack.push((node, children));
+        } else {
+            let size = node.read().expect("Failed to read loaded file").data.sizes;
+            add_to_parent(&directory, size, 1);
+        }
+    }
+
+    let errors = header
+        .errors
+        .into_iter()
+        .map(|(path, kind)| TraversalError {
+            path,
+            kind: ERROR_KINDS
+                .get(usize::from(kind))
+                .copied()
+                .unwrap_or(io::ErrorKind::Other),
+        })
+        .collect();
+    Ok(SnapshotContents {
+        tree,
+        info: SnapshotInfo {
+            path,
+            scanned_at: header.scanned_at,
+            exclude_patterns: header.exclude_patterns,
+            one_file_system: header.one_file_system,
+        },
+        excluded_count: header.excluded_count,
+        errors,
+    })
+}
+
+impl SnapshotEntry {
+    fn into_entry_node(self, path: PathBuf) -> EntryNode {
+        let is_directory = matches!(self.entry_type, EntryType::Directory);
+        EntryNode {
+            name: self.name,
+            path,
+            sizes: self.size,
+            dir_size: is_directory.then_some(self.size),
+            descendants_count: 0,
+            entry_type: self.entry_type,
+            metadata: self.metadata,
+            mount_point: self.mount_point,
+            incomplete: self.incomplete,
+            change: None,
+        }
+    }
+}
+
+/// Varint encoding keeps the many small numbers in the snapshot short.
+fn bincode_options() -> impl Options + Copy {
+    bincode::DefaultOptions::new()
+}
+
+fn add_to_parent(parent: &Arc<RwLock<Node<EntryNode>>>, size: EntrySize, descendants_count: usize) {
+    let mut parent = parent.write().expect("Failed to write loaded directory");
+    parent.data.sizes += size;
+    parent.data.descendants_count += descendants_count;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::backend::{disko_tree::DiskoTree, entry_node::Mode};
+
+    /// The fields of an entry which are kept in snapshots.
+    fn summary(node: &Arc<RwLock<Node<EntryNode>>>) -> String {
+        let entry = &node.read().unwrap().data;
+        format!(
+            "{} {:?} {:?} {:?} {:?} {} {:?} {} {}",
+            entry.name,
+            entry.path,
+            entry.entry_type,
+            entry.sizes,
+            entry.dir_size,
+            entry.descendants_count,
+            entry.metadata,
+            entry.mount_point,
+            entry.incomplete,
+        )
+    }
+
+    fn summaries(tree: &Tree<EntryNode>) -> Vec<String> {
+        let mut summaries = Vec::new();
+        let mut stack = vec![tree.get_root().unwrap()];
+        while let Some(node) = stack.pop() {
+            summaries.push(summary(&node));
+            stack.extend(node.read().unwrap().get_children().into_iter().rev());
+        }
+        summaries
+    }
+
+    /// `/scan` with a file, an unreadable directory with a hard linked file
+    /// and a mount point.
+    fn scanned_tree() -> Tree<EntryNode> {
+        let mut tree = Tree::new();
+        let root = tree
+            .create_node_and_set_root(EntryNode::for_test("/scan", EntryType::Directory, 4))
+            .unwrap();
+
+        let mut file = EntryNode::for_test("/scan/file", EntryType::File, 10);
+        file.metadata.modified = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        file.metadata.mode = Mode::Permissions(0o100_644);
+        DiskoTree::attach_for_test(&root, file);
+
+        let mut directory = EntryNode::for_test("/scan/directory", EntryType::Directory, 4);
+        directory.incomplete = true;
+        let directory = DiskoTree::attach_for_test(&root, directory);
+        let mut link = EntryNode::for_test("/scan/directory/link", EntryType::File, 20);
+        link.metadata.device = 3;
+        link.metadata.inode = 42;
+        link.metadata.links = 2;
+        DiskoTree::attach_for_test(&directory, link);
+
+        let mut mount = EntryNode::for_test("/scan/mount", EntryType::Directory, 4);
+        mount.mount_point = true;
+        DiskoTree::attach_for_test(&root, mount);
+        tree
+    }
+
+    fn save_tree(
+        tree: &Tree<EntryNode>,
+        options: &TraversalOptions,
+        errors: &[TraversalError],
+    ) -> Vec<u8> {
+        let mut snapshot = Vec::new();
+        save(
+            &tree.get_root().unwrap(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_800_000_000),
+            options,
+            7,
+            errors,
+            &mut snapshot,
+        )
+        .unwrap();
+        snapshot
+    }
+
+    fn load_snapshot(snapshot: &[u8]) -> Result<SnapshotContents> {
+        load(
+            PathBuf::from("scan.snapshot"),
+            snapshot,
+            snapshot.len() as u64,
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let tree = scanned_tree();
+        let options = TraversalOptions::new(vec!["*.tmp".to_string()], true).unwrap();
+        let errors = [
+            TraversalError {
+                path: PathBuf::from("/scan/directory/secret"),
+                kind: io::ErrorKind::PermissionDenied,
+            },
+            TraversalError {
+                path: PathBuf::from("/scan/busy"),
+                kind: io::ErrorKind::WouldBlock,
+            },
+        ];
+        let loaded = load_snapshot(&save_tree(&tree, &options, &errors)).unwrap();
+
+        assert_eq!(summaries(&loaded.tree), summaries(&tree));
+        assert_eq!(loaded.info.path, PathBuf::from("scan.snapshot"));
+        assert_eq!(
+            loaded.info.scanned_at,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_800_000_000)
+        );
+        assert_eq!(loaded.info.exclude_patterns, vec!["*.tmp"]);
+        assert!(loaded.info.one_file_system);
+        assert_eq!(loaded.excluded_count, 7);
+        assert_eq!(loaded.errors.len(), 2);
+        assert_eq!(
+            loaded.errors[0].path,
+            PathBuf::from("/scan/directory/secret")
+        );
+        assert_eq!(loaded.errors[0].kind, io::ErrorKind::PermissionDenied);
+        // Kinds which are not kept are loaded as `Other`.
+        assert_eq!(loaded.errors[1].kind, io::ErrorKind::Other);
+    }
+
+    #[test]
+    fn test_round_trip_sums_sizes() {
+        let loaded = load_snapshot(&save_tree(
+            &scanned_tree(),
+            &TraversalOptions::default(),
+            &[],
+        ))
+        .unwrap();
+        let root = loaded.tree.get_root().unwrap();
+        let root = root.read().unwrap();
+        assert_eq!(root.data.sizes.apparent_size, 4 + 10 + 4 + 20 + 4);
+        assert_eq!(root.data.sizes.inodes, 5);
+        assert_eq!(root.data.descendants_count, 4);
+        assert_eq!(root.data.dir_size.unwrap().apparent_size, 4);
+    }
+
+    #[test]
+    fn test_single_file() {
+        let mut tree = Tree::new();
+        tree.create_node_and_set_root(EntryNode::for_test("/file", EntryType::File, 10))
+            .unwrap();
+        let loaded = load_snapshot(&save_tree(&tree, &TraversalOptions::default(), &[])).unwrap();
+        assert_eq!(summaries(&loaded.tree), summaries(&tree));
+    }
+
+    #[test]
+    fn test_not_a_snapshot() {
+        let error = load_snapshot(b"{\"not\": \"a snapshot\"}").err().unwrap();
+        assert_eq!(error.to_string(), "Not a disko snapshot");
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut snapshot = save_tree(&scanned_tree(), &TraversalOptions::default(), &[]);
+        snapshot[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&2u32.to_le_bytes());
+        let error = load_snapshot(&snapshot).err().unwrap();
+        assert_eq!(error.to_string(), "Unsupported snapshot version 2");
+    }
+
+    #[test]
+    fn test_truncated() {
+        let snapshot = save_tree(&scanned_tree(), &TraversalOptions::default(), &[]);
+        for length in [0, MAGIC.len() + 2, snapshot.len() / 2, snapshot.len() - 1] {
+            assert!(load_snapshot(&snapshot[..length]).is_err(), "{length}");
+        }
+    }
+
+    #[test]
+    fn test_corrupt_length_is_limited() {
+        // A header whose root path claims to be longer than the snapshot.
+        let mut snapshot = MAGIC.to_vec();
+        snapshot.extend(VERSION.to_le_bytes());
+        // The varint marker of a u64, followed by the length.
+        snapshot.push(253);
+        snapshot.extend((u64::MAX / 2).to_le_bytes());
+        let error = load_snapshot(&snapshot).err().unwrap();
+        assert!(error.to_string().contains("size limit"), "{error}");
+    }
+}
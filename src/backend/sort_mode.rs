@@ -0,0 +1,227 @@
+use std::{cmp::Ordering, path::Path};
+
+use super::{entry_node::EntryNodeView, entry_size::SizeMetric};
+
+/// What the entries of a directory are ordered by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortKey {
+    #[default]
+    Size,
+    /// The name, with numbers compared by their value.
+    Name,
+    /// The modification time.
+    Modified,
+    /// The number of descendants.
+    Items,
+    /// The file extension, then the name.
+    Extension,
+    /// The change in size since the earlier scan, when comparing scans.
+    Growth,
+}
+
+/// The order in which the entries of a directory are listed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SortMode {
+    pub key: SortKey,
+    pub ascending: bool,
+}
+
+impl SortKey {
+    /// The key following this one when cycling through the keys.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Size => Self::Name,
+            Self::Name => Self::Modified,
+            Self::Modified => Self::Items,
+            Self::Items => Self::Extension,
+            Self::Extension => Self::Growth,
+            Self::Growth => Self::Size,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Size => "size",
+            Self::Name => "name",
+            Self::Modified => "mtime",
+            Self::Items => "items",
+            Self::Extension => "extension",
+            Self::Growth => "growth",
+        }
+    }
+
+    /// Whether the key is sorted in ascending order unless toggled, the
+    /// largest and newest entries come first while names are alphabetical.
+    fn ascending_by_default(self) -> bool {
+        matches!(self, Self::Name | Self::Extension)
+    }
+}
+
+impl Default for SortMode {
+    fn default() -> Self {
+        Self::new(SortKey::default())
+    }
+}
+
+impl SortMode {
+    pub fn new(key: SortKey) -> Self {
+        Self {
+            key,
+            ascending: key.ascending_by_default(),
+        }
+    }
+
+    /// Sort by the next key in its default direction.
+    pub fn next(self) -> Self {
+        Self::new(self.key.next())
+    }
+
+    pub fn toggle_direction(self) -> Self {
+        Self {
+            ascending: !self.ascending,
+            ..self
+        }
+    }
+
+    pub fn name(self) -> String {
+        let direction = if self.ascending {
+            "ascending"
+        } else {
+            "descending"
+        };
+        format!("{}, {direction}", self.key.name())
+    }
+
+    pub(crate) fn compare(
+        self,
+        a: &EntryNodeView,
+        b: &EntryNodeView,
+        size_metric: SizeMetric,
+    ) -> Ordering {
+        let ordering = match self.key {
+            SortKey::Size => a.sizes.get(size_metric).cmp(&b.sizes.get(size_metric)),
+            SortKey::Name => Ordering::Equal,
+            SortKey::Modified => a.modified.cmp(&b.modified),
+            SortKey::Items => a.descendants_count.cmp(&b.descendants_count),
+            SortKey::Extension => extension(&a.name).cmp(&extension(&b.name)),
+            SortKey::Growth => growth(a, size_metric).cmp(&growth(b, size_metric)),
+        }
+        .then_with(|| natural_cmp(&a.name, &b.name));
+
+        if self.ascending {
+            ordering
+        } else {
+            ordering.reverse()
+        }
+    }
+}
+
+fn growth(entry: &EntryNodeView, size_metric: SizeMetric) -> i128 {
+    entry.change.map_or(0, |change| change.delta(size_metric))
+}
+
+fn extension(name: &str) -> Option<String> {
+    Path::new(name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
+
+/// Compare the names ignoring case, with runs of digits compared by their
+/// value, so that `file2` comes before `file10`.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+    loop {
+        let (Some(&a_char), Some(&b_char)) = (a.peek(), b.peek()) else {
+            return a.peek().is_some().cmp(&b.peek().is_some());
+        };
+        if a_char.is_ascii_digit() && b_char.is_ascii_digit() {
+            let a_number = take_number(&mut a);
+            let b_number = take_number(&mut b);
+            let ordering = a_number
+                .len()
+                .cmp(&b_number.len())
+                .then_with(|| a_number.cmp(&b_number));
+            if ordering.is_ne() {
+                return ordering;
+            }
+            continue;
+        }
+        let ordering = a_char.to_lowercase().cmp(b_char.to_lowercase());
+        if ordering.is_ne() {
+            return ordering;
+        }
+        a.next();
+        b.next();
+    }
+}
+
+/// Take the run of digits without leading zeros.
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut number = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        if !(number.is_empty() && digit == '0') {
+            number.push(digit);
+        }
+    }
+    number
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn take(input: &str) -> (String, String) {
+        let mut chars = input.chars().peekable();
+        let number = take_number(&mut chars);
+        (number, chars.collect())
+    }
+
+    #[test]
+    fn test_take_number() {
+        assert_eq!(take("123abc"), ("123".to_string(), "abc".to_string()));
+        assert_eq!(take("abc"), (String::new(), "abc".to_string()));
+        assert_eq!(take(""), (String::new(), String::new()));
+    }
+
+    #[test]
+    fn test_take_number_skips_leading_zeros() {
+        assert_eq!(take("007.txt"), ("7".to_string(), ".txt".to_string()));
+        assert_eq!(take("0"), (String::new(), String::new()));
+        assert_eq!(take("100"), ("100".to_string(), String::new()));
+    }
+
+    #[test]
+    fn test_natural_cmp_numbers_by_value() {
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("file10", "file2"), Ordering::Greater);
+        assert_eq!(natural_cmp("a9b", "a10a"), Ordering::Less);
+        assert_eq!(natural_cmp("file1", "file01"), Ordering::Equal);
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_natural_cmp_text() {
+        assert_eq!(natural_cmp("abc", "abd"), Ordering::Less);
+        assert_eq!(natural_cmp("Abc", "abc"), Ordering::Equal);
+        assert_eq!(natural_cmp("b", "A"), Ordering::Greater);
+        assert_eq!(natural_cmp("", ""), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_natural_cmp_prefix_first() {
+        assert_eq!(natural_cmp("file", "file1"), Ordering::Less);
+        assert_eq!(natural_cmp("file1", "file"), Ordering::Greater);
+        assert_eq!(natural_cmp("", "a"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_natural_cmp_sorts() {
+        let mut names = vec!["img12.png", "IMG2.png", "img1.png", "img10.png", "img"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec!["img", "img1.png", "IMG2.png", "img10.png", "img12.png"]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Moving entries to the trash according to the
+//! [FreeDesktop.org Trash specification](https://specifications.freedesktop.org/trash-spec/trashspec-latest.html).
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// An entry which has been moved to the trash.
+#[derive(Clone, Debug)]
+pub(crate) struct TrashedEntry {
+    /// Where the entry was before it was trashed.
+    pub(crate) original_path: PathBuf,
+    /// Where the entry is stored in the trash.
+    pub(crate) trashed_path: PathBuf,
+    /// The `.trashinfo` file describing the entry.
+    pub(crate) info_path: PathBuf,
+}
+
+/// Whether moving to the trash is supported on this platform.
+pub(crate) fn is_supported() -> bool {
+    cfg!(unix)
+}
+
+/// Move the entry at the given path to the trash of the file system it is
+/// stored on.
+#[cfg(unix)]
+pub(crate) fn move_to_trash(path: &Path) -> io::Result<TrashedEntry> {
+    use std::{fs, io::Write};
+
+    let trash = unix::find_trash(path)?;
+    let files = trash.join("files");
+    let info = trash.join("info");
+
+    // The name is kept as is, names which are not valid UTF-8 would collide
+    // once converted.
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Cannot trash this path"))?;
+
+    // Reserve a unique name by atomically creating the info file first.
+    // Entries left in the trash without their info file keep their names.
+    let mut suffix = 1;
+    let (trashed_name, mut info_file, info_path) = loop {
+        let mut trashed_name = file_name.to_os_string();
+        if suffix > 1 {
+            trashed_name.push(format!(".{suffix}"));
+        }
+        if files.join(&trashed_name).symlink_metadata().is_ok() {
+            suffix += 1;
+            continue;
+        }
+        let mut info_name = trashed_name.clone();
+        info_name.push(".trashinfo");
+        let info_path = info.join(info_name);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(info_file) => break (trashed_name, info_file, info_path),
+            Err(error) if error.kind() == io::ErrorKind::AlreadyExists => suffix += 1,
+            Err(error) => return Err(error),
+        }
+    };
+
+    let trashed_path = files.join(trashed_name);
+    let written = write!(
+        info_file,
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        unix::percent_encode(path),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S"),
+    )
+    .and_then(|()| fs::rename(path, &trashed_path));
+
+    if let Err(error) = written {
+        let _ = fs::remove_file(&info_path);
+        return Err(error);
+    }
+
+    Ok(TrashedEntry {
+        original_path: path.to_path_buf(),
+        trashed_path,
+        info_path,
+    })
+}
+
+/// Move the trashed entry back to its original path.
+pub(crate) fn restore(entry: &TrashedEntry) -> io::Result<()> {
+    // Never overwrite whatever took the place of the entry in the meantime.
+    if entry.original_path.symlink_metadata().is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("'{}' already exists", entry.original_path.display()),
+        ));
+    }
+    std::fs::rename(&entry.trashed_path, &entry.original_path)?;
+    std::fs::remove_file(&entry.info_path)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn move_to_trash(_path: &Path) -> io::Result<TrashedEntry> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Moving to trash is not supported on this platform",
+    ))
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::{
+        env, fs, io,
+        os::unix::{
+            ffi::OsStrExt,
+            fs::{DirBuilderExt, MetadataExt, PermissionsExt},
+        },
+        path::{Path, PathBuf},
+    };
+
+    const STICKY_BIT: u32 = 0o1000;
+
+    /// Find the trash directory for the entry at the given path and create
+    /// its `files` and `info` directories. The first of the candidates which
+    /// can be created is used.
+    pub(super) fn find_trash(path: &Path) -> io::Result<PathBuf> {
+        let mut last_error = None;
+        for trash in trash_candidates(path)? {
+            let created = create_private_dir_all(&trash.join("files"))
+                .and_then(|()| create_private_dir_all(&trash.join("info")));
+            match created {
+                Ok(()) => return Ok(trash),
+                Err(error) => last_error = Some(error),
+            }
+        }
+        Err(last_error.expect("There is always a trash candidate"))
+    }
+
+    /// The trash directories for the entry at the given path, in the order
+    /// they should be tried.
+    ///
+    /// The home trash is used for entries on the same file system as the
+    /// home trash. Otherwise the user's directory in the shared `.Trash` of
+    /// the top directory of the entry's file system is tried, and
+    /// `.Trash-$uid` in the top directory if the shared trash is missing,
+    /// fails the checks or the user's directory cannot be created.
+    fn trash_candidates(path: &Path) -> io::Result<Vec<PathBuf>> {
+        let device = fs::symlink_metadata(path)?.dev();
+
+        let home_trash = home_trash()?;
+        let home_trash_device = home_trash
+            .ancestors()
+            .find_map(|ancestor| fs::metadata(ancestor).ok())
+            .map(|metadata| metadata.dev());
+        if home_trash_device == Some(device) {
+            return Ok(vec![home_trash]);
+        }
+
+        let top_dir = top_dir(path, device)?;
+        // SAFETY: `getuid` is always successful and has no side effects.
+        let uid = unsafe { libc::getuid() };
+        let mut trashes = Vec::new();
+
+        // An administrator created trash shared by all users. It must not be
+        // a symbolic link and must have the sticky bit set.
+        let shared_trash = top_dir.join(".Trash");
+        if let Ok(metadata) = fs::symlink_metadata(&shared_trash) {
+            if metadata.is_dir() && metadata.permissions().mode() & STICKY_BIT != 0 {
+                trashes.push(shared_trash.join(uid.to_string()));
+            }
+        }
+
+        trashes.push(top_dir.join(format!(".Trash-{uid}")));
+        Ok(trashes)
+    }
+
+    fn home_trash() -> io::Result<PathBuf> {
+        let data_home = env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "HOME is not set"))?;
+        Ok(data_home.join("Trash"))
+    }
+
+    /// The mount point of the file system the path is stored on.
+    fn top_dir(path: &Path, device: u64) -> io::Result<PathBuf> {
+        let mut top_dir = path
+            .parent()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Cannot trash root"))?;
+        while let Some(parent) = top_dir.parent() {
+            if fs::metadata(parent)?.dev() != device {
+                break;
+            }
+            top_dir = parent;
+        }
+        Ok(top_dir.to_path_buf())
+    }
+
+    fn create_private_dir_all(path: &Path) -> io::Result<()> {
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(path)
+    }
+
+    /// Escape the path the same way URLs are escaped.
+    pub(super) fn percent_encode(path: &Path) -> String {
+        let mut encoded = String::new();
+        for &byte in path.as_os_str().as_bytes() {
+            if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{byte:02X}"));
+            }
+        }
+        encoded
+    }
+}
@@ -0,0 +1,51 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+/// Options changing which entries are visited during the traversal.
+#[derive(Clone, Default)]
+pub(crate) struct TraversalOptions {
+    /// Glob patterns as given by the user.
+    pub(crate) exclude_patterns: Vec<String>,
+    /// Do not descend into directories on other file systems.
+    pub(crate) one_file_system: bool,
+    /// Patterns containing a path separator, matched against the full path.
+    excluded_paths: GlobSet,
+    /// Patterns without a path separator, matched against the entry name.
+    excluded_names: GlobSet,
+}
+
+impl TraversalOptions {
+    pub(crate) fn new(exclude_patterns: Vec<String>, one_file_system: bool) -> Result<Self> {
+        let mut excluded_paths = GlobSetBuilder::new();
+        let mut excluded_names = GlobSetBuilder::new();
+
+        for pattern in &exclude_patterns {
+            let glob = Glob::new(pattern)
+                .with_context(|| format!("Invalid exclude pattern '{pattern}'"))?;
+            if pattern.contains(std::path::is_separator) {
+                excluded_paths.add(glob);
+            } else {
+                excluded_names.add(glob);
+            }
+        }
+
+        Ok(Self {
+            exclude_patterns,
+            one_file_system,
+            excluded_paths: excluded_paths.build()?,
+            excluded_names: excluded_names.build()?,
+        })
+    }
+
+    /// Check whether the entry at the given path should be skipped
+    /// together with all of its descendants.
+    pub(crate) fn is_excluded(&self, path: &Path) -> bool {
+        if self.excluded_paths.is_match(path) {
+            return true;
+        }
+        path.file_name()
+            .is_some_and(|name| self.excluded_names.is_match(name))
+    }
+}
@@ -1,16 +1,21 @@
 use std::{
     fmt::{Debug, Formatter},
-    fs,
-    sync::{Arc, RwLock},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
+    },
 };
 
 #[cfg(unix)]
-use std::{
-    collections::{hash_map, HashMap},
-    sync::Mutex,
-};
+use std::collections::{hash_map, HashMap, HashSet};
 
-use super::entry_node::EntryNode;
+use super::{
+    entry_node::{EntryMetadata, EntryNode},
+    largest_files::SharedLargestFiles,
+    traversal_options::TraversalOptions,
+};
 use ref_tree::{Node, Tree};
 
 #[derive(Clone)]
@@ -19,29 +24,124 @@ pub(crate) enum TreeWalkAncestor {
     Parent(Arc<RwLock<Node<EntryNode>>>),
 }
 
+/// An entry which could not be read during the traversal.
+#[derive(Clone, Debug)]
+pub struct TraversalError {
+    pub path: PathBuf,
+    pub kind: io::ErrorKind,
+}
+
+pub(crate) type TraversalErrors = Arc<Mutex<Vec<TraversalError>>>;
+
 #[derive(Clone)]
 pub(crate) struct TreeWalkState {
     pub(crate) ancestor: TreeWalkAncestor,
+    options: Arc<TraversalOptions>,
+    excluded_count: Arc<AtomicUsize>,
+    errors: TraversalErrors,
+    largest_files: SharedLargestFiles,
+    /// Device of the traversal root, set only when the traversal should stay
+    /// on one file system.
+    #[cfg(unix)]
+    root_device: Option<u64>,
     #[cfg(unix)]
     inodes_unvisited_links: Arc<Mutex<HashMap<u64, u64>>>,
+    /// Hard linked inodes whose size is already counted by an entry outside
+    /// of the traversed directory.
+    #[cfg(unix)]
+    counted_hard_links: Arc<HashSet<u64>>,
 }
 
 impl TreeWalkState {
-    pub fn new(tree: Arc<RwLock<Tree<EntryNode>>>) -> Self {
+    pub fn new(
+        ancestor: TreeWalkAncestor,
+        root: &Path,
+        options: Arc<TraversalOptions>,
+        excluded_count: Arc<AtomicUsize>,
+        errors: TraversalErrors,
+        largest_files: SharedLargestFiles,
+        counted_hard_links: HashSet<u64>,
+    ) -> Self {
+        #[cfg(unix)]
+        let root_device = if options.one_file_system {
+            use std::os::unix::fs::MetadataExt;
+            fs::metadata(root).ok().map(|metadata| metadata.dev())
+        } else {
+            None
+        };
+        #[cfg(not(unix))]
+        let _ = (root, counted_hard_links);
+
         Self {
-            ancestor: TreeWalkAncestor::Tree(tree),
+            ancestor,
+            options,
+            excluded_count,
+            errors,
+            largest_files,
+            #[cfg(unix)]
+            root_device,
             #[cfg(unix)]
             inodes_unvisited_links: Arc::new(Mutex::new(HashMap::new())),
+            #[cfg(unix)]
+            counted_hard_links: Arc::new(counted_hard_links),
         }
     }
 
+    /// Check whether the entry should be skipped according to the traversal
+    /// options. Skipped entries are counted.
+    pub fn exclude(&self, path: &Path) -> bool {
+        if !self.options.is_excluded(path) {
+            return false;
+        }
+        self.excluded_count.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Remember that the entry at the given path could not be read.
+    pub fn record_error(&self, path: PathBuf, kind: io::ErrorKind) {
+        self.errors
+            .lock()
+            .expect("Failed to lock traversal errors.")
+            .push(TraversalError { path, kind });
+    }
+
+    /// Keep the file if it is among the largest files seen so far.
+    pub fn offer_largest_file(&self, file: &EntryNode) {
+        self.largest_files
+            .lock()
+            .expect("Failed to lock largest files.")
+            .offer(&file.path, file.sizes);
+    }
+
+    /// Whether the traversal should not descend into other file systems.
+    pub fn stays_on_one_file_system(&self) -> bool {
+        self.options.one_file_system
+    }
+
+    /// Check whether the directory lies on a different file system than the
+    /// traversal root. Always `false` unless the traversal should stay on one
+    /// file system.
     #[cfg(unix)]
-    pub fn file_has_been_seen(&mut self, metadata: &fs::Metadata) -> bool {
+    pub fn is_on_other_file_system(&self, metadata: &fs::Metadata) -> bool {
         use std::os::unix::fs::MetadataExt;
 
-        let inode = metadata.ino();
-        let links = metadata.nlink();
+        self.root_device
+            .is_some_and(|root_device| metadata.dev() != root_device)
+    }
+
+    #[cfg(not(unix))]
+    pub fn is_on_other_file_system(&self, _metadata: &fs::Metadata) -> bool {
+        false
+    }
+
+    #[cfg(unix)]
+    pub fn file_has_been_seen(&mut self, metadata: &EntryMetadata) -> bool {
+        let inode = metadata.inode;
+        let links = metadata.links;
         if links > 1 {
+            if self.counted_hard_links.contains(&inode) {
+                return true;
+            }
             let mut inodes_unvisited_links = self
                 .inodes_unvisited_links
                 .lock()
@@ -73,7 +173,7 @@ impl TreeWalkState {
     /// https://doc.rust-lang.org/std/os/windows/fs/trait.MetadataExt.html#tymethod.file_index
     /// However, it is nightly-only experimental API
     #[cfg(not(unix))]
-    pub fn file_has_been_seen(&mut self, _metadata: &fs::Metadata) -> bool {
+    pub fn file_has_been_seen(&mut self, _metadata: &EntryMetadata) -> bool {
         false
     }
 }
@@ -82,8 +182,16 @@ impl Default for TreeWalkState {
     fn default() -> Self {
         Self {
             ancestor: TreeWalkAncestor::Tree(Arc::new(RwLock::new(Tree::new()))),
+            options: Arc::new(TraversalOptions::default()),
+            excluded_count: Arc::new(AtomicUsize::new(0)),
+            errors: TraversalErrors::default(),
+            largest_files: SharedLargestFiles::default(),
+            #[cfg(unix)]
+            root_device: None,
             #[cfg(unix)]
             inodes_unvisited_links: Arc::new(Mutex::new(HashMap::new())),
+            #[cfg(unix)]
+            counted_hard_links: Arc::default(),
         }
     }
 }
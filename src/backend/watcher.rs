@@ -0,0 +1,179 @@
+//! Watching the scanned directories for changes with inotify.
+//!
+//! The watcher only collects the paths of the changed entries, the changes
+//! are applied to the tree by `DiskoTree::apply_watched_changes`, so that
+//! they do not interfere with other modifications of the tree.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+
+use crate::ui::event_handling::DiskoEvent;
+
+/// How long to wait before looking for new events when there are none.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const EVENT_BUFFER_SIZE: usize = 64 * 1024;
+
+type Directories = Arc<Mutex<HashMap<WatchDescriptor, PathBuf>>>;
+
+/// Collects the paths of entries created, deleted or modified in the
+/// watched directories on a separate thread.
+pub(crate) struct Watcher {
+    watches: Watches,
+    /// The watched directories by their watch descriptors.
+    directories: Directories,
+    changes: Arc<Mutex<BTreeSet<PathBuf>>>,
+    /// Whether events have been lost because the event queue overflowed.
+    overflowed: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    handler: Option<thread::JoinHandle<()>>,
+}
+
+impl Watcher {
+    /// Start collecting the changes. The sender is notified whenever there
+    /// are new changes to be applied.
+    pub(crate) fn new(sender: mpsc::Sender<DiskoEvent>) -> io::Result<Self> {
+        let inotify = Inotify::init()?;
+        let mut watcher = Self {
+            watches: inotify.watches(),
+            directories: Directories::default(),
+            changes: Arc::default(),
+            overflowed: Arc::default(),
+            stop: Arc::new(AtomicBool::new(false)),
+            handler: None,
+        };
+
+        let directories = watcher.directories.clone();
+        let changes = watcher.changes.clone();
+        let overflowed = watcher.overflowed.clone();
+        let stop = watcher.stop.clone();
+        watcher.handler = Some(thread::spawn(move || {
+            Self::collect_changes(inotify, &directories, &changes, &overflowed, &stop, &sender);
+        }));
+        Ok(watcher)
+    }
+
+    /// Watch the directory, but not its subdirectories.
+    /// Fails with `io::ErrorKind::StorageFull` once the limit of watches has
+    /// been reached.
+    pub(crate) fn watch(&mut self, directory: &Path) -> io::Result<()> {
+        let mask = WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MODIFY
+            | WatchMask::CLOSE_WRITE
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO
+            | WatchMask::DONT_FOLLOW
+            | WatchMask::ONLYDIR;
+        let descriptor = self.watches.add(directory, mask)?;
+        self.directories
+            .lock()
+            .expect("Failed to lock watched directories")
+            .insert(descriptor, directory.to_path_buf());
+        Ok(())
+    }
+
+    /// Stop watching the directory and all directories below it.
+    pub(crate) fn unwatch_subtree(&mut self, directory: &Path) {
+        let mut directories = self
+            .directories
+            .lock()
+            .expect("Failed to lock watched directories");
+        let descriptors: Vec<WatchDescriptor> = directories
+            .iter()
+            .filter(|(_, path)| path.starts_with(directory))
+            .map(|(descriptor, _)| descriptor.clone())
+            .collect();
+        for descriptor in descriptors {
+            directories.remove(&descriptor);
+            // The watch is already gone if the directory has been deleted.
+            let _ = self.watches.remove(descriptor);
+        }
+    }
+
+    /// Take the paths of the entries changed since the last call, parents
+    /// before their children.
+    pub(crate) fn take_changes(&self) -> BTreeSet<PathBuf> {
+        std::mem::take(&mut *self.changes.lock().expect("Failed to lock changes"))
+    }
+
+    /// Whether events have been lost since the last call. The changes they
+    /// described cannot be recovered, only a rescan brings the sizes up to
+    /// date again.
+    pub(crate) fn take_overflow(&self) -> bool {
+        self.overflowed.swap(false, Ordering::Relaxed)
+    }
+
+    fn collect_changes(
+        mut inotify: Inotify,
+        directories: &Directories,
+        changes: &Mutex<BTreeSet<PathBuf>>,
+        overflowed: &AtomicBool,
+        stop: &AtomicBool,
+        sender: &mpsc::Sender<DiskoEvent>,
+    ) {
+        let mut buffer = vec![0; EVENT_BUFFER_SIZE];
+        while !stop.load(Ordering::Relaxed) {
+            let events = match inotify.read_events(&mut buffer) {
+                Ok(events) => events,
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+                Err(_) => return,
+            };
+
+            let mut directories = directories
+                .lock()
+                .expect("Failed to lock watched directories");
+            let mut changes = changes.lock().expect("Failed to lock changes");
+            // Notify only about the first change, the following ones are
+            // applied together with it.
+            let should_notify = changes.is_empty() && !overflowed.load(Ordering::Relaxed);
+            let mut lost_events = false;
+            for event in events {
+                if event.mask.contains(EventMask::Q_OVERFLOW) {
+                    lost_events = true;
+                    continue;
+                }
+                if event.mask.contains(EventMask::IGNORED) {
+                    directories.remove(&event.wd);
+                    continue;
+                }
+                let Some(directory) = directories.get(&event.wd) else {
+                    continue;
+                };
+                changes.insert(match event.name {
+                    Some(name) => directory.join(name),
+                    None => directory.clone(),
+                });
+            }
+            if lost_events {
+                overflowed.store(true, Ordering::Relaxed);
+            }
+            if should_notify && (lost_events || !changes.is_empty()) {
+                // Here we just ignore if the event handler has stopped.
+                let _ = sender.send(DiskoEvent::FileSystemChanged);
+            }
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handler) = self.handler.take() {
+            handler.join().expect("Failed to join watcher thread.");
+        }
+    }
+}
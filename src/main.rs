@@ -3,20 +3,35 @@ mod backend;
 /// The front end of the application.
 pub mod ui;
 
-use std::path::PathBuf;
+use std::{
+    env,
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
 use anyhow::{bail, Result};
-use ui::app::App;
+use ui::{app::App, color_theme::ColorTheme, config::Config, key_map::KeyMaps};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
-use crate::backend::disko_tree::DiskoTree;
+use crate::backend::{
+    disko_tree::{DeletionMode, DiskoTree},
+    export::ExportFormat,
+    largest_files::DEFAULT_LARGEST_FILES_COUNT,
+    trash,
+    traversal_options::TraversalOptions,
+};
 
 const DEFAULT_RENDER_RATE: u64 = 30;
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
 struct Arguments {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// The path to the directory to be analyzed
     #[arg(default_value = ".")]
     path: PathBuf,
@@ -33,39 +48,205 @@ struct Arguments {
     #[arg(short, long, default_value_t = 4)]
     tick_rate: u64,
 
-    /// Enable black and white mode.
-    #[arg(long, default_value = "false")]
+    /// Enable black and white mode, the same as --theme black-and-white.
+    /// Also enabled by setting NO_COLOR
+    #[arg(long, default_value = "false", conflicts_with = "theme")]
     black_and_white: bool,
+
+    /// The color theme: dark, light, black-and-white or one defined in the
+    /// config file
+    #[arg(long, value_name = "NAME")]
+    theme: Option<String>,
+
+    /// Skip entries matching the glob pattern, including their contents.
+    /// Patterns without a path separator are matched against entry names,
+    /// others against full paths. Can be used multiple times.
+    #[arg(short, long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Do not descend into directories on other file systems.
+    #[arg(short = 'x', long)]
+    one_file_system: bool,
+
+    /// Do not open a terminal UI, export the whole scanned tree to the file
+    /// instead. Supported formats: json, ncdu.
+    #[arg(long, num_args = 2, value_names = ["FORMAT", "FILE"])]
+    export: Option<Vec<String>>,
+
+    /// Browse an ncdu export file instead of scanning the file system.
+    /// Deletion is disabled for imported trees.
+    #[arg(long, value_name = "FILE", conflicts_with = "path")]
+    import: Option<PathBuf>,
+
+    /// Do not open a terminal UI, save a snapshot of the scanned tree to the
+    /// file instead, so it can be browsed later with --load-snapshot.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["import", "export"])]
+    save_snapshot: Option<PathBuf>,
+
+    /// Browse a snapshot saved with --save-snapshot instead of scanning the
+    /// file system. Deletion is disabled for snapshots.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["path", "import", "save_snapshot"])]
+    load_snapshot: Option<PathBuf>,
+
+    /// Delete entries permanently instead of moving them to the trash.
+    #[arg(long)]
+    no_trash: bool,
+
+    /// Keep the sizes up to date by watching the scanned directories for
+    /// changes once they are traversed. Only supported on Linux.
+    #[arg(short, long, conflicts_with_all = ["import", "load_snapshot"])]
+    watch: bool,
+
+    /// How many of the largest files of the whole tree are listed.
+    #[arg(long, value_name = "COUNT", default_value_t = DEFAULT_LARGEST_FILES_COUNT)]
+    largest_files: usize,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compare two scans of the same directory to see what has grown.
+    ///
+    /// Each scan is either a snapshot file or a directory to scan now.
+    Diff {
+        /// The earlier scan
+        old: PathBuf,
+        /// The later scan
+        new: PathBuf,
+    },
 }
 
 fn main() -> Result<()> {
-    let mut arguments = Arguments::parse();
+    let arguments = Arguments::parse();
+
+    if arguments.threads == 0 {
+        bail!("threads must be greater than 0");
+    }
 
-    arguments.path = dunce::canonicalize(arguments.path)?;
+    if arguments.one_file_system && !cfg!(unix) {
+        bail!("--one-file-system is not supported on this platform");
+    }
 
-    if !arguments.path.is_dir() {
-        bail!("{} is not a directory", arguments.path.display());
+    if arguments.watch && !cfg!(target_os = "linux") {
+        bail!("--watch is not supported on this platform");
     }
 
-    if arguments.threads == 0 {
-        bail!("threads must be greater than 0");
+    // The config only matters to the terminal UI. It is read before scanning
+    // so that mistakes in it show up right away.
+    let (key_maps, colors, opener) =
+        if arguments.summary || arguments.export.is_some() || arguments.save_snapshot.is_some() {
+            (KeyMaps::default(), ColorTheme::default(), String::new())
+        } else {
+            let config = Config::load()?;
+            let theme = theme_name(&arguments, &config);
+            (
+                KeyMaps::new(&config.keys)?,
+                ColorTheme::named(theme, &config.themes)?,
+                config.opener().to_string(),
+            )
+        };
+
+    let mut tree = if let Some(Command::Diff { old, new }) = &arguments.command {
+        if arguments.import.is_some()
+            || arguments.load_snapshot.is_some()
+            || arguments.save_snapshot.is_some()
+            || arguments.export.is_some()
+            || arguments.watch
+        {
+            bail!("diff only compares the scans given to it");
+        }
+        DiskoTree::from_diff(open_scan(old, &arguments)?, open_scan(new, &arguments)?)?
+    } else if let Some(import) = arguments.import {
+        DiskoTree::from_ncdu_export(import)?
+    } else if let Some(snapshot) = arguments.load_snapshot {
+        DiskoTree::from_snapshot(snapshot)?
+    } else {
+        let path = dunce::canonicalize(&arguments.path)?;
+
+        if !path.is_dir() {
+            bail!("{} is not a directory", path.display());
+        }
+
+        let traversal_options =
+            TraversalOptions::new(arguments.exclude.clone(), arguments.one_file_system)?;
+        DiskoTree::new(path, arguments.threads, traversal_options)
+    };
+    tree.set_largest_files_count(arguments.largest_files);
+
+    if let Some(export) = arguments.export {
+        let [format, file] = <[String; 2]>::try_from(export)
+            .expect("clap should always provide exactly two export values");
+        let format: ExportFormat = format.parse()?;
+        let writer = BufWriter::new(File::create(&file)?);
+        tree.traverse();
+        tree.export(format, writer)?;
+        return Ok(());
     }
 
-    let mut tree = DiskoTree::new(arguments.path, arguments.threads);
+    if let Some(snapshot) = arguments.save_snapshot {
+        let writer = BufWriter::new(File::create(&snapshot)?);
+        let scanned_at = SystemTime::now();
+        tree.traverse();
+        tree.save_snapshot(scanned_at, writer)?;
+        return Ok(());
+    }
 
     if arguments.summary {
         tree.traverse();
         println!("{tree}");
+        let error_count = tree.traversal_error_count();
+        if error_count > 0 {
+            eprintln!("warning: {error_count} entries could not be read, sizes may be incomplete");
+        }
         return Ok(());
     }
 
+    // Never delete permanently unless asked to.
+    if !arguments.no_trash && !trash::is_supported() && !tree.is_read_only() {
+        bail!("Moving to the trash is not supported on this platform, pass --no-trash to delete permanently");
+    }
+    let deletion_mode = if arguments.no_trash {
+        DeletionMode::Permanent
+    } else {
+        DeletionMode::Trash
+    };
+
     // Create and start the application.
     let mut app = App::new(
         arguments.tick_rate,
         DEFAULT_RENDER_RATE,
         tree,
-        arguments.black_and_white,
+        colors,
+        deletion_mode,
+        arguments.watch,
+        key_maps,
+        opener,
     )?;
     app.run()?;
     Ok(())
 }
+
+/// Get the name of the theme to use. A theme on the command line wins over
+/// NO_COLOR, which wins over the theme in the config.
+fn theme_name<'a>(arguments: &'a Arguments, config: &'a Config) -> &'a str {
+    let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    if let Some(theme) = &arguments.theme {
+        theme
+    } else if arguments.black_and_white || no_color {
+        "black-and-white"
+    } else {
+        config.theme.as_deref().unwrap_or("dark")
+    }
+}
+
+/// Load the snapshot at the path, or scan the directory at the path.
+fn open_scan(path: &Path, arguments: &Arguments) -> Result<DiskoTree> {
+    if !path.is_dir() {
+        return DiskoTree::from_snapshot(path.to_path_buf());
+    }
+    let path = dunce::canonicalize(path)?;
+    let traversal_options =
+        TraversalOptions::new(arguments.exclude.clone(), arguments.one_file_system)?;
+    let mut tree = DiskoTree::new(path, arguments.threads, traversal_options);
+    tree.traverse();
+    Ok(tree)
+}
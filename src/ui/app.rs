@@ -1,26 +1,56 @@
-use std::{fs::File, io::Read, sync::mpsc};
+use std::{
+    env,
+    fs::File,
+    io::Read,
+    mem,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
 
-use ratatui::{backend::CrosstermBackend, layout::Rect, style::Color, Terminal};
+use ratatui::{backend::CrosstermBackend, layout::Rect, Terminal};
 
 use crate::backend::{
-    disko_tree::DiskoTree,
+    deletion::{DeletionBatch, DeletionProgress},
+    disko_tree::{DeletionMode, DiskoTree, TreeSource},
+    duplicates::DuplicateGroup,
     entry_node::{EntryNodeView, EntryType},
+    entry_size::{EntrySize, SizeMetric},
+    search::SearchQuery,
+    sort_mode::{SortKey, SortMode},
+    tree_walk_state::TraversalError,
 };
 
 use super::{
     color_theme::ColorTheme,
-    components::{confirm_delete::ConfirmDeletePopup, indicator, table::StatefulTable},
+    components::{
+        confirm_delete::ConfirmDeletePopup,
+        deletion_errors::DeletionErrorsPopup,
+        deletion_progress::DeletionProgressPopup,
+        filter::Filter,
+        help::HelpPopup,
+        indicator,
+        screen_areas::ScreenAreas,
+        search::{SearchInput, SearchResults},
+        table::StatefulTable,
+    },
     disko_event_handling::DiskoEventHandler,
     event_handling::{DiskoEvent, EventHandler},
+    key_map::KeyMaps,
     renderer,
     tui::Tui,
 };
 
 use anyhow::Result;
+use byte_unit::Byte;
 
 pub type CrosstermTerminal = ratatui::Terminal<ratatui::backend::CrosstermBackend<std::io::Stdout>>;
 
 const CLEAR_MESSAGE_AFTER_SECONDS: u64 = 2;
+/// The longest time between two clicks on the same row to count as a double
+/// click.
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(500);
 
 /// All possible application actions.
 #[derive(Clone)]
@@ -28,29 +58,99 @@ pub enum Action {
     Tick,
     Quit,
     SetTraversalFinished,
+    ApplyWatchedChanges,
     Resize(u16, u16),
     ShowMainScreen,
     ShowConfirmDeletePopup,
     BufferInput(String),
     InvalidInput(String),
+    /// Run the action bound to the typed sequence of keys.
+    FinishInput(String, Box<Action>),
     FocusNextItem,
     FocusPreviousItem,
-    FocusFirstItem(String),
+    FocusFirstItem,
     FocusLastItem,
     EnterFocusedDirectory,
     EnterParentDirectory,
     DeletePopupSwitchConfirmation,
     DeletePopupSelect,
     ConfirmDelete,
+    UpdateDeletionProgress(DeletionProgress),
+    FinishDeletion,
+    CancelDeletion,
+    ScrollDown,
+    ScrollUp,
+    /// Left click at the given column and row of the terminal.
+    Click {
+        column: u16,
+        row: u16,
+    },
+    ScrollWheelDown {
+        column: u16,
+        row: u16,
+    },
+    ScrollWheelUp {
+        column: u16,
+        row: u16,
+    },
     ToggleSelection,
+    UndoDeletion,
+    ShowTraversalErrors,
+    ShowFilter,
+    FilterInput(char),
+    FilterDeleteChar,
+    FilterSwitchKind,
+    ApplyFilter,
+    ClearFilter,
+    /// Clear the filter if there is one, otherwise quit.
+    ClearFilterOrQuit,
+    FocusNextMatch,
+    FocusPreviousMatch,
+    ShowSearch,
+    SearchInput(char),
+    SearchDeleteChar,
+    SearchSwitchKind,
+    RunSearch,
+    JumpToSearchResult,
+    ToggleLargestFiles,
+    /// Start looking for duplicates, cancel the search if it is running or
+    /// leave the listing of the duplicates.
+    FindDuplicates,
+    FinishDuplicateSearch,
+    RescanFocusedDirectory,
+    RescanRoot,
+    /// Open the focused entry with the opener from the config.
+    OpenFocusedEntry,
+    /// Open the focused file in `$EDITOR`.
+    EditFocusedFile,
+    /// Start `$SHELL` in the focused directory.
+    OpenShell,
     SwitchEntryDisplaySize,
+    CycleSortMode,
+    ToggleSortDirection,
     SwitchProgress,
+    ShowHelp,
+    CloseHelp,
 }
 
 /// Possible application main screen states.
 pub enum Main {
     Table(StatefulTable<EntryNodeView>),
     EmptyDirectory,
+    /// No entry matches the filter.
+    NoMatches,
+}
+
+/// What the main screen lists.
+#[derive(Default)]
+pub enum Listing {
+    /// The children of the current directory.
+    #[default]
+    Directory,
+    /// The largest files of the whole tree.
+    LargestFiles,
+    /// The files with the same contents, one group after another.
+    Duplicates(Vec<DuplicateGroup>),
 }
 
 /// Possible application preview states.
@@ -64,7 +164,14 @@ pub enum Preview {
 pub enum AppFocus {
     MainScreen,
     ConfirmDeletePopup(ConfirmDeletePopup),
+    DeletionProgressPopup(DeletionProgressPopup),
+    DeletionErrorsPopup(DeletionErrorsPopup),
+    TraversalErrors(StatefulTable<TraversalError>),
     BufferingInput,
+    Filter,
+    Search,
+    SearchResults(SearchResults),
+    Help(HelpPopup),
 }
 
 /// Application state.
@@ -76,10 +183,25 @@ pub struct AppState {
     pub current_directory: EntryNodeView,
     pub traversal_finished: bool,
     pub show_bar: bool,
-    pub show_disk_size: bool,
+    /// Whether the changes in the file system are applied to the tree.
+    pub watching: bool,
+    pub listing: Listing,
+    pub finding_duplicates: bool,
+    pub size_metric: SizeMetric,
+    pub sort_mode: SortMode,
+    pub filter: Filter,
+    pub search: SearchInput,
     pub message: String,
     pub clear_message_ticks: u64,
     pub indicator: indicator::Indicator,
+    pub excluded_count: usize,
+    pub error_count: usize,
+    pub source: TreeSource,
+    pub deletion_mode: DeletionMode,
+    /// Deletions which can be undone, the most recent one last.
+    pub deletion_batches: Vec<DeletionBatch>,
+    /// Filled in by the renderer for each frame.
+    pub areas: ScreenAreas,
 }
 
 /// Application.
@@ -89,15 +211,26 @@ pub struct App {
     disko_events: DiskoEventHandler,
     tree: DiskoTree,
     tick_rate: u64,
+    /// Whether to watch the file system for changes once it is traversed.
+    watch: bool,
+    /// The command opening files with their default application.
+    opener: String,
+    /// When and on which entry the main table was last clicked.
+    last_click: Option<(Instant, PathBuf)>,
 }
 
 impl App {
     /// Constructs a new instance of [`App`].
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         tick_rate: u64,
         render_rate: u64,
         tree: DiskoTree,
-        black_and_white: bool,
+        colors: ColorTheme,
+        deletion_mode: DeletionMode,
+        watch: bool,
+        key_maps: KeyMaps,
+        opener: String,
     ) -> Result<Self> {
         // Initialize the terminal user interface.
         let backend = CrosstermBackend::new(std::io::stdout());
@@ -106,22 +239,7 @@ impl App {
         let (sender, receiver) = mpsc::channel();
         let events = EventHandler::new(tick_rate, render_rate, sender, receiver);
 
-        let color_scheme = if black_and_white {
-            ColorTheme::new(
-                Color::White,
-                Color::White,
-                Color::White,
-                Color::White,
-                Color::Black,
-                Color::Black,
-                Color::White,
-                false,
-            )
-        } else {
-            ColorTheme::default()
-        };
-
-        let renderer = renderer::Renderer::new(color_scheme);
+        let renderer = renderer::Renderer::new(colors, &key_maps);
         let tui = Tui::new(terminal, events, renderer);
 
         let state = AppState {
@@ -132,13 +250,30 @@ impl App {
             current_directory: EntryNodeView::new_dir(tree.root_path()),
             traversal_finished: false,
             show_bar: false,
-            show_disk_size: false,
+            watching: false,
+            listing: Listing::default(),
+            finding_duplicates: false,
+            size_metric: SizeMetric::default(),
+            // What has grown the most comes first when comparing scans.
+            sort_mode: if tree.is_diff() {
+                SortMode::new(SortKey::Growth)
+            } else {
+                SortMode::default()
+            },
+            filter: Filter::default(),
+            search: SearchInput::default(),
             message: String::new(),
             clear_message_ticks: 0,
             indicator: indicator::Indicator::new(indicator::ASCII, "Traversing".to_string()),
+            excluded_count: 0,
+            error_count: 0,
+            source: tree.source().clone(),
+            deletion_mode,
+            deletion_batches: Vec::new(),
+            areas: ScreenAreas::default(),
         };
 
-        let disko_events = DiskoEventHandler::default();
+        let disko_events = DiskoEventHandler::new(key_maps);
 
         Ok(Self {
             state,
@@ -146,6 +281,9 @@ impl App {
             disko_events,
             tree,
             tick_rate,
+            watch,
+            opener,
+            last_click: None,
         })
     }
 
@@ -170,7 +308,8 @@ impl App {
                 entry
                     .index_to_original_node
                     .expect("should never get the root directory as a child"),
-                self.state.show_disk_size,
+                self.state.sort_mode,
+                self.state.size_metric,
             )
             .expect("child directory at the given index should exist");
 
@@ -205,6 +344,10 @@ impl App {
         }
 
         self.tree.stop_background_traversal();
+        self.tree.cancel_background_deletion();
+        self.tree.finish_background_deletion();
+        self.tree.cancel_duplicate_search();
+        self.tree.finish_duplicate_search();
 
         // Exit the user interface.
         self.tui.exit()?;
@@ -212,15 +355,37 @@ impl App {
     }
 
     pub fn update_view_on_switch_dir(&mut self) {
-        let Some((current_directory, entries)) =
-            self.tree.get_current_dir_view(self.state.show_disk_size)
-        else {
+        // The filter only applies to the directory it was typed in.
+        self.state.filter.clear();
+        self.update_view_from_top();
+    }
+
+    /// Get the view of the current directory and the entries to be listed.
+    fn get_view(&mut self) -> Option<(EntryNodeView, Vec<EntryNodeView>)> {
+        match &mut self.state.listing {
+            Listing::Directory => self
+                .tree
+                .get_current_dir_view(self.state.sort_mode, self.state.size_metric),
+            Listing::LargestFiles => self
+                .tree
+                .get_largest_files_view(self.state.sort_mode, self.state.size_metric),
+            Listing::Duplicates(groups) => self.tree.get_duplicates_view(groups),
+        }
+    }
+
+    /// Update the view and focus the first entry.
+    pub fn update_view_from_top(&mut self) {
+        let Some((current_directory, entries)) = self.get_view() else {
             return;
         };
         self.state.current_directory = current_directory;
+        let is_empty = entries.is_empty();
+        let entries = self.state.filter.apply(entries);
         self.state.main = {
-            if entries.is_empty() {
+            if is_empty {
                 Main::EmptyDirectory
+            } else if entries.is_empty() {
+                Main::NoMatches
             } else {
                 Main::Table(StatefulTable::with_focused(entries, Some(0)))
             }
@@ -229,30 +394,41 @@ impl App {
     }
 
     pub fn update_view(&mut self) {
-        let Some((current_directory, entries)) =
-            self.tree.get_current_dir_view(self.state.show_disk_size)
-        else {
+        let Some((current_directory, entries)) = self.get_view() else {
             return;
         };
         self.state.current_directory = current_directory;
+        self.state.excluded_count = self.tree.excluded_count();
+        self.state.error_count = self.tree.traversal_error_count();
 
         if entries.is_empty() {
             self.state.main = Main::EmptyDirectory;
             return;
         }
+        let entries = self.state.filter.apply(entries);
+        if entries.is_empty() {
+            self.state.main = Main::NoMatches;
+            return;
+        }
 
         let current_focus = if let Main::Table(table) = &self.state.main {
-            table.focused_index()
+            table
+                .focused_index()
+                .zip(table.focused().map(|entry| entry.path.clone()))
         } else {
             None
         };
 
         let focused_index = {
-            // If there was a focus prior, try to focus the same entry again.
+            // If there was a focus prior, try to focus the same entry again,
+            // it may have moved when the entries were sorted differently.
             // It's possible that there were multiple entries removed, so the focus
             // is out of bounds now. In that case, focus the last entry.
-            if let Some(index) = current_focus {
-                Some(index.min(entries.len() - 1))
+            if let Some((index, path)) = current_focus {
+                entries
+                    .iter()
+                    .position(|entry| entry.path == path)
+                    .or(Some(index.min(entries.len() - 1)))
             }
             // There was no focus prior, so focus the first entry.
             else {
@@ -274,6 +450,9 @@ impl App {
             self.state.clear_message_ticks += 1;
         }
 
+        if self.state.finding_duplicates {
+            self.state.indicator.next_step();
+        }
         if self.state.traversal_finished {
             return;
         }
@@ -328,15 +507,34 @@ impl App {
                 Action::SetTraversalFinished => {
                     self.set_message("Traversal finished".to_string());
                     self.state.traversal_finished = true;
+                    if self.watch {
+                        self.start_watching();
+                    }
                     self.update_view();
                 }
+                Action::ApplyWatchedChanges => {
+                    if self.tree.apply_watched_changes() {
+                        self.update_view();
+                    }
+                    if self.tree.watch_events_lost() {
+                        let rescan = match self
+                            .disko_events
+                            .key_map(&AppFocus::MainScreen)
+                            .keys_of(&Action::RescanRoot)
+                            .next()
+                        {
+                            Some(keys) => format!("press {keys} to rescan"),
+                            None => "rescan to update the sizes".to_string(),
+                        };
+                        self.set_message(format!("Watch events lost, {rescan}"));
+                    }
+                }
                 Action::Resize(w, h) => self.resize(w, h)?,
                 Action::ShowMainScreen => {
                     self.state.focus = AppFocus::MainScreen;
                 }
                 Action::ShowConfirmDeletePopup => {
-                    if !self.state.traversal_finished {
-                        self.set_message("Cannot delete while traversing".to_string());
+                    if !self.can_delete() {
                         return Ok(());
                     }
 
@@ -370,12 +568,15 @@ impl App {
                     }
                     self.update_focus();
                 }
-                Action::FocusFirstItem(input) => {
+                Action::FinishInput(input, action) => {
                     self.set_message(input);
+                    self.state.focus = AppFocus::MainScreen;
+                    return self.update(Some(*action));
+                }
+                Action::FocusFirstItem => {
                     if let Main::Table(table) = &mut self.state.main {
                         table.focus_first();
                     }
-                    self.state.focus = AppFocus::MainScreen;
                     self.update_focus();
                 }
                 Action::FocusLastItem => {
@@ -391,16 +592,61 @@ impl App {
                 }
                 Action::DeletePopupSelect => {
                     if let AppFocus::ConfirmDeletePopup(popup) = &mut self.state.focus {
-                        if popup.confirmed() {
+                        let confirmed = popup.confirmed();
+                        self.state.focus = AppFocus::MainScreen;
+                        if confirmed {
                             self.delete_selected();
                         }
-                        self.state.focus = AppFocus::MainScreen;
                     }
                 }
                 Action::ConfirmDelete => {
-                    self.delete_selected();
                     self.state.focus = AppFocus::MainScreen;
+                    self.delete_selected();
+                }
+                Action::UpdateDeletionProgress(progress) => {
+                    if let AppFocus::DeletionProgressPopup(popup) = &mut self.state.focus {
+                        popup.update(progress);
+                    }
+                    self.update_view();
+                }
+                Action::FinishDeletion => self.finish_deletion(),
+                Action::CancelDeletion => {
+                    if let AppFocus::DeletionProgressPopup(popup) = &mut self.state.focus {
+                        self.tree.cancel_background_deletion();
+                        popup.cancel();
+                    }
+                }
+                Action::ScrollDown => match &mut self.state.focus {
+                    AppFocus::DeletionErrorsPopup(popup) => popup.scroll_down(),
+                    AppFocus::TraversalErrors(errors) => errors.focus_next(),
+                    AppFocus::SearchResults(results) => results.table.focus_next(),
+                    AppFocus::Help(help) => help.scroll_down(),
+                    _ => {}
+                },
+                Action::ScrollUp => match &mut self.state.focus {
+                    AppFocus::DeletionErrorsPopup(popup) => popup.scroll_up(),
+                    AppFocus::TraversalErrors(errors) => errors.focus_previous(),
+                    AppFocus::SearchResults(results) => results.table.focus_previous(),
+                    AppFocus::Help(help) => help.scroll_up(),
+                    _ => {}
+                },
+                Action::ShowHelp => {
+                    if !matches!(self.state.focus, AppFocus::Help(_)) {
+                        let previous = mem::replace(&mut self.state.focus, AppFocus::MainScreen);
+                        let key_map = self.disko_events.key_map(&previous);
+                        self.state.focus = AppFocus::Help(HelpPopup::new(previous, key_map));
+                    }
+                }
+                Action::CloseHelp => {
+                    if let AppFocus::Help(help) =
+                        mem::replace(&mut self.state.focus, AppFocus::MainScreen)
+                    {
+                        self.state.focus = help.into_previous();
+                    }
                 }
+                Action::Click { column, row } => self.click(column, row)?,
+                Action::ScrollWheelDown { column, row } => self.scroll_wheel(column, row, true),
+                Action::ScrollWheelUp { column, row } => self.scroll_wheel(column, row, false),
                 Action::ToggleSelection => {
                     if !self.state.traversal_finished {
                         self.set_message("Cannot select while traversing".to_string());
@@ -413,14 +659,38 @@ impl App {
                     };
 
                     if let Some(focused) = table.focused_index() {
+                        if let Listing::Duplicates(groups) = &self.state.listing {
+                            if !table.is_selected(focused) && !keeps_a_copy(groups, table, focused)
+                            {
+                                self.set_message("Keep at least one copy".to_string());
+                                return Ok(());
+                            }
+                        }
                         table.toggle_selection(focused);
                     };
                 }
+                Action::UndoDeletion => self.undo_deletion(),
+                Action::ShowTraversalErrors => {
+                    let errors = self.tree.traversal_errors();
+                    if errors.is_empty() {
+                        self.set_message("No traversal errors".to_string());
+                        return Ok(());
+                    }
+                    self.state.focus =
+                        AppFocus::TraversalErrors(StatefulTable::with_focused(errors, Some(0)));
+                }
                 Action::EnterFocusedDirectory => {
                     let Main::Table(table) = &mut self.state.main else {
                         return Ok(());
                     };
 
+                    if !matches!(self.state.listing, Listing::Directory) {
+                        if let Some(path) = table.focused().map(|entry| entry.path.clone()) {
+                            self.jump_to_entry(&path);
+                        }
+                        return Ok(());
+                    }
+
                     if let Some(focused) = table.focused() {
                         if !matches!(focused.entry_type, EntryType::Directory) {
                             return Ok(());
@@ -440,6 +710,11 @@ impl App {
                     }
                 }
                 Action::EnterParentDirectory => {
+                    if !matches!(self.state.listing, Listing::Directory) {
+                        self.state.listing = Listing::Directory;
+                        self.update_view_on_switch_dir();
+                        return Ok(());
+                    }
                     // Ignore if there is no parent anymore.
                     if self.tree.switch_to_parent_directory().is_ok() {
                         self.update_view_on_switch_dir();
@@ -448,8 +723,94 @@ impl App {
                         }
                     }
                 }
+                Action::ShowFilter => self.state.focus = AppFocus::Filter,
+                Action::FilterInput(c) => {
+                    self.state.filter.push(c);
+                    self.update_view_from_top();
+                }
+                Action::FilterDeleteChar => {
+                    self.state.filter.pop();
+                    self.update_view_from_top();
+                }
+                Action::FilterSwitchKind => {
+                    self.state.filter.switch_kind();
+                    self.update_view_from_top();
+                }
+                Action::ApplyFilter => {
+                    self.state.focus = AppFocus::MainScreen;
+                    if !self.state.filter.is_active() {
+                        self.clear_filter();
+                    }
+                }
+                Action::ClearFilter => self.clear_filter(),
+                Action::ClearFilterOrQuit => {
+                    if self.state.filter.is_active() {
+                        self.clear_filter();
+                    } else {
+                        self.quit();
+                    }
+                }
+                Action::FocusNextMatch | Action::FocusPreviousMatch => {
+                    if !self.state.filter.is_active() {
+                        self.set_message("No filter, press / to filter".to_string());
+                    } else if let Main::Table(table) = &mut self.state.main {
+                        if let Action::FocusNextMatch = action {
+                            table.focus_next();
+                        } else {
+                            table.focus_previous();
+                        }
+                        self.update_focus();
+                    }
+                }
+                Action::ShowSearch => self.state.focus = AppFocus::Search,
+                Action::SearchInput(c) => self.state.search.push(c),
+                Action::SearchDeleteChar => self.state.search.pop(),
+                Action::SearchSwitchKind => self.state.search.switch_kind(),
+                Action::RunSearch => self.run_search(),
+                Action::JumpToSearchResult => self.jump_to_search_result(),
+                Action::ToggleLargestFiles => {
+                    self.state.listing = match self.state.listing {
+                        Listing::LargestFiles => Listing::Directory,
+                        _ => Listing::LargestFiles,
+                    };
+                    self.update_view_on_switch_dir();
+                }
+                Action::FindDuplicates => self.find_duplicates(),
+                Action::FinishDuplicateSearch => self.finish_duplicate_search(),
+                Action::RescanFocusedDirectory => {
+                    let focused = match &self.state.main {
+                        Main::Table(table) => table.focused(),
+                        _ => None,
+                    };
+                    match focused {
+                        Some(entry) if matches!(entry.entry_type, EntryType::Directory) => {
+                            let path = entry.path.clone();
+                            self.rescan(&path);
+                        }
+                        _ => self.set_message("Focus a directory to rescan".to_string()),
+                    }
+                }
+                Action::RescanRoot => self.rescan(&self.tree.root_path()),
+                Action::OpenFocusedEntry => self.open_focused_entry()?,
+                Action::EditFocusedFile => self.edit_focused_file()?,
+                Action::OpenShell => self.open_shell()?,
                 Action::SwitchEntryDisplaySize => {
-                    self.state.show_disk_size = !self.state.show_disk_size;
+                    self.state.size_metric = self.state.size_metric.next();
+                    self.set_message(format!("Showing {}", self.state.size_metric.name()));
+                    self.update_view();
+                }
+                Action::CycleSortMode => {
+                    self.state.sort_mode = self.state.sort_mode.next();
+                    // Growth only exists when comparing two scans.
+                    if self.state.sort_mode.key == SortKey::Growth && !self.tree.is_diff() {
+                        self.state.sort_mode = self.state.sort_mode.next();
+                    }
+                    self.set_message(format!("Sorted by {}", self.state.sort_mode.name()));
+                    self.update_view();
+                }
+                Action::ToggleSortDirection => {
+                    self.state.sort_mode = self.state.sort_mode.toggle_direction();
+                    self.set_message(format!("Sorted by {}", self.state.sort_mode.name()));
                     self.update_view();
                 }
                 Action::SwitchProgress => self.state.show_bar = !self.state.show_bar,
@@ -458,7 +819,29 @@ impl App {
         Ok(())
     }
 
+    /// Whether the tree can be deleted from, the reason is shown if not.
+    fn can_delete(&mut self) -> bool {
+        if self.tree.is_read_only() {
+            self.set_message("Imported tree is read-only".to_string());
+            return false;
+        }
+        if !self.state.traversal_finished {
+            self.set_message("Cannot delete while traversing".to_string());
+            return false;
+        }
+        true
+    }
+
     pub fn delete_selected(&mut self) {
+        // The confirmation may have been skipped by binding confirm_delete to
+        // a key of another screen.
+        if !self.can_delete() {
+            return;
+        }
+        if !matches!(self.state.listing, Listing::Directory) {
+            self.delete_selected_paths();
+            return;
+        }
         let Main::Table(table) = &self.state.main else {
             return;
         };
@@ -485,9 +868,478 @@ impl App {
             }
         }
 
-        if self.tree.delete_entries(indices).is_err() {
-            self.set_message("Error deleting entry".to_string());
+        let total = table
+            .items
+            .iter()
+            .filter(|entry| {
+                entry
+                    .index_to_original_node
+                    .is_some_and(|index| indices.contains(&index))
+            })
+            .fold(EntrySize::default(), |total, entry| total + entry.sizes);
+
+        let sender = self.tui.events.get_event_sender();
+        match self
+            .tree
+            .start_background_deletion(indices, self.state.deletion_mode, sender)
+        {
+            Ok(()) => {
+                self.state.focus =
+                    AppFocus::DeletionProgressPopup(DeletionProgressPopup::new(total));
+            }
+            Err(_) => self.set_message("Error deleting entry".to_string()),
+        }
+    }
+
+    /// Delete the selected entries by their paths, for listings which do not
+    /// belong to a single directory.
+    fn delete_selected_paths(&mut self) {
+        let Main::Table(table) = &self.state.main else {
+            return;
+        };
+
+        let mut entries = table.selected();
+        // If no items where selected, delete the focused one
+        if entries.is_empty() {
+            match table.focused() {
+                Some(entry) => entries = vec![entry],
+                None => return,
+            }
+        }
+
+        let paths: Vec<PathBuf> = entries.iter().map(|entry| entry.path.clone()).collect();
+        let total = entries
+            .iter()
+            .fold(EntrySize::default(), |total, entry| total + entry.sizes);
+
+        let sender = self.tui.events.get_event_sender();
+        match self
+            .tree
+            .start_background_deletion_of_paths(&paths, self.state.deletion_mode, sender)
+        {
+            Ok(()) => {
+                self.state.focus =
+                    AppFocus::DeletionProgressPopup(DeletionProgressPopup::new(total));
+            }
+            Err(_) => self.set_message("Error deleting entry".to_string()),
+        }
+    }
+
+    fn run_search(&mut self) {
+        let search = &mut self.state.search;
+        let query = match SearchQuery::parse(search.input(), search.kind()) {
+            Ok(query) => query,
+            Err(error) => {
+                search.set_error(format!("{error:#}"));
+                return;
+            }
+        };
+        let results = self.tree.search(&query, self.state.size_metric);
+        self.state.focus = AppFocus::SearchResults(SearchResults::new(
+            self.state.search.input().to_string(),
+            self.tree.root_path(),
+            results,
+        ));
+    }
+
+    /// Show the directory containing the focused search result.
+    fn jump_to_search_result(&mut self) {
+        let AppFocus::SearchResults(results) = &self.state.focus else {
+            return;
+        };
+        let Some(path) = results.table.focused().map(|entry| entry.path.clone()) else {
+            return;
+        };
+        self.jump_to_entry(&path);
+    }
+
+    /// Show the directory containing the entry and focus the entry.
+    fn jump_to_entry(&mut self, path: &Path) {
+        let Some(parent) = path.parent() else {
+            return;
+        };
+        if let Err(error) = self.tree.switch_to_directory(parent) {
+            self.set_message(format!("{error:#}"));
+            return;
+        }
+
+        self.state.focus = AppFocus::MainScreen;
+        self.state.listing = Listing::Directory;
+        self.update_view_on_switch_dir();
+        if let Main::Table(table) = &mut self.state.main {
+            let index = table.items.iter().position(|entry| entry.path == path);
+            table.state.select(index.or(Some(0)));
+        }
+        self.update_focus();
+    }
+
+    /// Handle a left click at the given column and row of the terminal.
+    fn click(&mut self, column: u16, row: u16) -> Result<()> {
+        if let AppFocus::ConfirmDeletePopup(_) = self.state.focus {
+            return match self.state.areas.confirm_delete_button_at(column, row) {
+                Some(true) => self.update(Some(Action::ConfirmDelete)),
+                Some(false) => self.update(Some(Action::ShowMainScreen)),
+                None => Ok(()),
+            };
+        }
+
+        if let Some(ancestor) = self.state.areas.breadcrumb_at(column, row) {
+            let ancestor = ancestor.to_path_buf();
+            self.enter_ancestor(&ancestor);
+            return Ok(());
+        }
+
+        let Some(visible_row) = self.state.areas.main_table_row_at(column, row) else {
+            return Ok(());
+        };
+        let Main::Table(table) = &mut self.state.main else {
+            return Ok(());
+        };
+        let index = table.state.offset() + visible_row;
+        let Some(path) = table.items.get(index).map(|entry| entry.path.clone()) else {
+            return Ok(());
+        };
+
+        let now = Instant::now();
+        let is_double_click = self.last_click.as_ref().is_some_and(|(time, clicked)| {
+            *clicked == path && now.duration_since(*time) <= DOUBLE_CLICK_INTERVAL
+        });
+        table.state.select(Some(index));
+        self.update_focus();
+        if is_double_click {
+            self.last_click = None;
+            return self.update(Some(Action::EnterFocusedDirectory));
+        }
+        self.last_click = Some((now, path));
+        Ok(())
+    }
+
+    /// Move the focus of the main or the preview table, whichever is under
+    /// the pointer.
+    fn scroll_wheel(&mut self, column: u16, row: u16, down: bool) {
+        let scroll = |table: &mut StatefulTable<EntryNodeView>| {
+            if down {
+                table.focus_next_without_wrapping();
+            } else {
+                table.focus_previous_without_wrapping();
+            }
+        };
+
+        if self.state.areas.is_over_preview_table(column, row) {
+            if let Preview::Table(table) = &mut self.state.preview {
+                scroll(table);
+            }
+        } else if self.state.areas.main_table_row_at(column, row).is_some() {
+            if let Main::Table(table) = &mut self.state.main {
+                scroll(table);
+            }
+            self.update_focus();
+        }
+    }
+
+    /// Show the given ancestor of the current directory and focus the
+    /// directory leading back down.
+    fn enter_ancestor(&mut self, ancestor: &Path) {
+        // The path may lead above the scanned tree.
+        if !ancestor.starts_with(self.tree.root_path()) {
+            return;
+        }
+        let current = self.state.current_directory.path.clone();
+        if let Some(child) = current
+            .ancestors()
+            .find(|path| path.parent() == Some(ancestor))
+        {
+            self.jump_to_entry(child);
+        }
+    }
+
+    fn start_watching(&mut self) {
+        let sender = self.tui.events.get_event_sender();
+        match self.tree.start_watching(sender) {
+            Ok((watched, total)) if watched < total => {
+                self.set_message(format!(
+                    "Watch limit reached, watching {watched} of {total} directories"
+                ));
+                self.state.watching = true;
+            }
+            Ok(_) => self.state.watching = true,
+            Err(error) => self.set_message(format!("{error:#}")),
+        }
+    }
+
+    /// Scan the directory at the given path again, the whole tree if it is
+    /// the root.
+    fn rescan(&mut self, path: &Path) {
+        if self.state.finding_duplicates {
+            self.set_message("Cannot rescan while looking for duplicates".to_string());
+            return;
+        }
+
+        let sender = self.tui.events.get_event_sender();
+        match self.tree.start_background_rescan(path, sender) {
+            // The view is updated while the rescan runs, so that the focus
+            // stays on the directory once it is attached again.
+            Ok(()) => {
+                self.state.traversal_finished = false;
+                self.state.indicator.label = "Rescanning".to_string();
+                self.set_message(format!("Rescanning {}", path.display()));
+            }
+            Err(error) => {
+                self.set_message(format!("{error:#}"));
+                // The directory may have vanished.
+                self.update_view();
+            }
+        }
+    }
+
+    /// The entry focused in the main table.
+    fn focused_entry(&self) -> Option<&EntryNodeView> {
+        match &self.state.main {
+            Main::Table(table) => table.focused(),
+            _ => None,
+        }
+    }
+
+    fn open_focused_entry(&mut self) -> Result<()> {
+        let Some(path) = self.focused_entry().map(|entry| entry.path.clone()) else {
+            return Ok(());
+        };
+        let Some(mut command) = parse_command(&self.opener) else {
+            self.set_message("No opener is set".to_string());
+            return Ok(());
+        };
+        command.arg(&path);
+        if self.run_in_foreground(command)? {
+            self.rescan_containing_directory(&path);
+        }
+        Ok(())
+    }
+
+    fn edit_focused_file(&mut self) -> Result<()> {
+        let path = match self.focused_entry() {
+            Some(entry) if matches!(entry.entry_type, EntryType::File) => entry.path.clone(),
+            _ => {
+                self.set_message("Focus a file to edit".to_string());
+                return Ok(());
+            }
+        };
+        let editor = env_command("EDITOR").unwrap_or_else(|| "vi".to_string());
+        let Some(mut command) = parse_command(&editor) else {
+            return Ok(());
+        };
+        command.arg(&path);
+        if self.run_in_foreground(command)? {
+            self.rescan_containing_directory(&path);
+        }
+        Ok(())
+    }
+
+    fn open_shell(&mut self) -> Result<()> {
+        let path = match self.focused_entry() {
+            Some(entry) if matches!(entry.entry_type, EntryType::Directory) => entry.path.clone(),
+            _ => {
+                self.set_message("Focus a directory to open a shell in".to_string());
+                return Ok(());
+            }
+        };
+        let shell = env_command("SHELL").unwrap_or_else(|| "sh".to_string());
+        let Some(mut command) = parse_command(&shell) else {
+            return Ok(());
+        };
+        command.current_dir(&path);
+        // The focused directory is never the root, so only it is rescanned.
+        if self.run_in_foreground(command)? && !self.tree.is_read_only() {
+            self.rescan(&path);
         }
+        Ok(())
+    }
+
+    /// Rescan the directory containing the entry, as the command which has
+    /// been run on it may have created or deleted files next to it. The
+    /// whole tree is rescanned when that directory is the root.
+    fn rescan_containing_directory(&mut self, path: &Path) {
+        if self.tree.is_read_only() {
+            return;
+        }
+        let root = self.tree.root_path();
+        let directory = match path.parent() {
+            Some(parent) if path != root && parent.starts_with(&root) => parent,
+            _ => &root,
+        };
+        self.rescan(directory);
+    }
+
+    /// Hand the terminal over to the command until it exits. Returns whether
+    /// the command could be run, a failure is shown as the message.
+    fn run_in_foreground(&mut self, mut command: Command) -> Result<bool> {
+        let program = command.get_program().to_string_lossy().into_owned();
+        self.tui.suspend()?;
+        let status = command.status();
+        self.tui.resume()?;
+
+        let status = match status {
+            Ok(status) => status,
+            Err(error) => {
+                self.set_message(format!("Failed to run {program}: {error}"));
+                return Ok(false);
+            }
+        };
+        if !status.success() {
+            self.set_message(format!("{program} failed: {status}"));
+        }
+        Ok(true)
+    }
+
+    fn find_duplicates(&mut self) {
+        if self.state.finding_duplicates {
+            self.tree.cancel_duplicate_search();
+            return;
+        }
+        if let Listing::Duplicates(_) = self.state.listing {
+            self.state.listing = Listing::Directory;
+            self.update_view_on_switch_dir();
+            return;
+        }
+        if !self.state.traversal_finished {
+            self.set_message("Cannot look for duplicates while traversing".to_string());
+            return;
+        }
+
+        let sender = self.tui.events.get_event_sender();
+        match self.tree.start_duplicate_search(sender) {
+            Ok(()) => {
+                self.state.finding_duplicates = true;
+                self.state.indicator.label = "Finding duplicates".to_string();
+            }
+            Err(error) => self.set_message(format!("{error:#}")),
+        }
+    }
+
+    /// Show the duplicates found by the finished search.
+    fn finish_duplicate_search(&mut self) {
+        self.state.finding_duplicates = false;
+        let Some(groups) = self.tree.finish_duplicate_search() else {
+            self.set_message("Cancelled looking for duplicates".to_string());
+            return;
+        };
+        if groups.is_empty() {
+            self.set_message("No duplicates found".to_string());
+            return;
+        }
+
+        let wasted = Byte::from_u64(groups.iter().map(DuplicateGroup::wasted).sum())
+            .get_appropriate_unit(byte_unit::UnitType::Decimal);
+        self.set_message(format!(
+            "Duplicate groups: {}, {wasted:.2} can be freed",
+            groups.len()
+        ));
+        self.state.listing = Listing::Duplicates(groups);
+        self.update_view_on_switch_dir();
+    }
+
+    fn clear_filter(&mut self) {
+        self.state.focus = AppFocus::MainScreen;
+        self.state.filter.clear();
+        self.update_view();
+    }
+
+    /// Collect the outcome of the finished deletion.
+    fn finish_deletion(&mut self) {
+        self.state.focus = AppFocus::MainScreen;
+        let Some(report) = self.tree.finish_background_deletion() else {
+            return;
+        };
+
+        let freed = Byte::from_u64(report.progress.freed.get(self.state.size_metric.in_bytes()))
+            .get_appropriate_unit(byte_unit::UnitType::Decimal);
+        if report.cancelled {
+            self.set_message(format!("Cancelled, freed {freed:.2}"));
+        } else {
+            self.set_message(format!("Freed {freed:.2}"));
+        }
+
+        if !report.batch.is_empty() {
+            self.state.deletion_batches.push(report.batch);
+        }
+        if !report.failures.is_empty() {
+            self.state.focus =
+                AppFocus::DeletionErrorsPopup(DeletionErrorsPopup::new(report.failures));
+        }
+        // The changes are not applied while deleting.
+        self.tree.apply_watched_changes();
         self.update_view();
     }
+
+    /// Restore the entries removed by the most recent deletion.
+    fn undo_deletion(&mut self) {
+        // A rescan could pick up the restored entries a second time.
+        if !self.state.traversal_finished {
+            self.set_message("Cannot undo while traversing".to_string());
+            return;
+        }
+        let Some(batch) = self.state.deletion_batches.pop() else {
+            self.set_message("Nothing to undo".to_string());
+            return;
+        };
+
+        let report = self.tree.restore_batch(batch);
+        let retry = if report.failed.is_empty() {
+            ""
+        } else {
+            ", press u to retry"
+        };
+        match report.errors.as_slice() {
+            [] if report.restored == 1 => self.set_message("Restored 1 item".to_string()),
+            [] => self.set_message(format!("Restored {} items", report.restored)),
+            [error] => self.set_message(format!("{error:#}{retry}")),
+            [error, others @ ..] => {
+                self.set_message(format!("{error:#} and {} more{retry}", others.len()))
+            }
+        }
+        // The entries which could not be restored can be tried again.
+        if !report.failed.is_empty() {
+            self.state.deletion_batches.push(report.failed);
+        }
+        self.update_view();
+    }
+}
+
+/// Whether selecting the duplicate at the given index would leave at least
+/// one file of its group unselected.
+fn keeps_a_copy(
+    groups: &[DuplicateGroup],
+    table: &StatefulTable<EntryNodeView>,
+    index: usize,
+) -> bool {
+    let Some(entry) = table.items.get(index) else {
+        return true;
+    };
+    let Some(group) = groups
+        .iter()
+        .find(|group| group.paths.contains(&entry.path))
+    else {
+        return true;
+    };
+    let selected = table
+        .selected()
+        .iter()
+        .filter(|entry| group.paths.contains(&entry.path))
+        .count();
+    selected + 1 < group.paths.len()
+}
+
+/// The command in the environment variable, `None` if it is not set.
+fn env_command(variable: &str) -> Option<String> {
+    env::var(variable)
+        .ok()
+        .filter(|command| !command.trim().is_empty())
+}
+
+/// Split the command into the program and its arguments at whitespace, the
+/// way commands like `$EDITOR` are usually given.
+fn parse_command(command: &str) -> Option<Command> {
+    let mut words = command.split_whitespace();
+    let mut command = Command::new(words.next()?);
+    command.args(words);
+    Some(command)
 }
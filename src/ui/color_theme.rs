@@ -1,5 +1,13 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Context, Result};
 use ratatui::style::Color;
 
+use super::config::{ColorValue, ThemeConfig};
+
+/// The names of the built-in themes.
+pub const BUILTIN_THEMES: [&str; 3] = ["dark", "light", "black-and-white"];
+
 pub struct ColorTheme {
     /// Primary color of the application, used for directories and their focus.
     pub primary: Color,
@@ -17,6 +25,14 @@ pub struct ColorTheme {
     pub highlight: Color,
     /// Whether to use a colorful progress bar and percentages.
     pub colorful_bar: bool,
+    /// Entries missing from the earlier scan when comparing scans.
+    pub added: Color,
+    /// Entries missing from the later scan when comparing scans.
+    pub removed: Color,
+    /// Entries which have grown since the earlier scan.
+    pub grown: Color,
+    /// Entries which have shrunk since the earlier scan.
+    pub shrunk: Color,
 }
 
 impl ColorTheme {
@@ -40,10 +56,123 @@ impl ColorTheme {
             secondary_bg,
             highlight,
             colorful_bar,
+            // The changes are told apart by their signs and markers only.
+            added: fg,
+            removed: fg,
+            grown: fg,
+            shrunk: fg,
         }
     }
 }
 
+impl ColorTheme {
+    /// For terminals with a light background.
+    pub fn light() -> Self {
+        Self {
+            primary: Color::Blue,
+            secondary: Color::DarkGray,
+            tertiary: Color::Magenta,
+            fg: Color::Black,
+            primary_bg: Color::White,
+            secondary_bg: Color::Gray,
+            highlight: Color::Red,
+            colorful_bar: true,
+            added: Color::Red,
+            removed: Color::Green,
+            grown: Color::Indexed(88),
+            shrunk: Color::Indexed(22),
+        }
+    }
+
+    pub fn black_and_white() -> Self {
+        Self::new(
+            Color::White,
+            Color::White,
+            Color::White,
+            Color::White,
+            Color::Black,
+            Color::Black,
+            Color::White,
+            false,
+        )
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::default()),
+            "light" => Some(Self::light()),
+            "black-and-white" => Some(Self::black_and_white()),
+            _ => None,
+        }
+    }
+
+    /// Get the theme of the given name from the themes in the config or from
+    /// the built-in themes.
+    pub fn named(name: &str, themes: &BTreeMap<String, ThemeConfig>) -> Result<Self> {
+        let Some(config) = themes.get(name) else {
+            return Self::builtin(name).with_context(|| {
+                format!(
+                    "Unknown theme '{name}', the built-in themes are {}",
+                    BUILTIN_THEMES.join(", ")
+                )
+            });
+        };
+
+        // A theme named after a built-in one changes the built-in one.
+        let base = config.base.as_deref().unwrap_or(match Self::builtin(name) {
+            Some(_) => name,
+            None => "dark",
+        });
+        let Some(mut theme) = Self::builtin(base) else {
+            bail!(
+                "Theme '{name}' is based on '{base}', but only the built-in themes {} can be",
+                BUILTIN_THEMES.join(", ")
+            );
+        };
+
+        for (field, color, value) in [
+            ("primary", &mut theme.primary, &config.primary),
+            ("secondary", &mut theme.secondary, &config.secondary),
+            ("tertiary", &mut theme.tertiary, &config.tertiary),
+            ("fg", &mut theme.fg, &config.fg),
+            ("primary_bg", &mut theme.primary_bg, &config.primary_bg),
+            (
+                "secondary_bg",
+                &mut theme.secondary_bg,
+                &config.secondary_bg,
+            ),
+            ("highlight", &mut theme.highlight, &config.highlight),
+            ("added", &mut theme.added, &config.added),
+            ("removed", &mut theme.removed, &config.removed),
+            ("grown", &mut theme.grown, &config.grown),
+            ("shrunk", &mut theme.shrunk, &config.shrunk),
+        ] {
+            if let Some(value) = value {
+                *color = parse_color(value)
+                    .with_context(|| format!("Invalid {field} color of theme '{name}'"))?;
+            }
+        }
+        if let Some(colorful_bar) = config.colorful_bar {
+            theme.colorful_bar = colorful_bar;
+        }
+        Ok(theme)
+    }
+}
+
+/// Parse a color name like `light-blue`, a 256-color index or `#rrggbb`.
+fn parse_color(value: &ColorValue) -> Result<Color> {
+    let name = match value {
+        ColorValue::Index(index) => return Ok(Color::Indexed(*index)),
+        ColorValue::Name(name) => name,
+    };
+    let error = || anyhow!("Unknown color '{name}', use a name, a 256-color index or #rrggbb");
+    // The hex colors are cut into bytes.
+    if !name.is_ascii() {
+        return Err(error());
+    }
+    name.parse().map_err(|_| error())
+}
+
 impl Default for ColorTheme {
     fn default() -> Self {
         Self {
@@ -55,6 +184,10 @@ impl Default for ColorTheme {
             secondary_bg: Color::DarkGray,
             highlight: Color::Red,
             colorful_bar: true,
+            added: Color::LightRed,
+            removed: Color::LightGreen,
+            grown: Color::Red,
+            shrunk: Color::Green,
         }
     }
 }
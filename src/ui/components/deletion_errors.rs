@@ -0,0 +1,33 @@
+use crate::backend::deletion::DeletionFailure;
+
+pub struct DeletionErrorsPopup {
+    failures: Vec<DeletionFailure>,
+    scroll: usize,
+}
+
+impl DeletionErrorsPopup {
+    pub fn new(failures: Vec<DeletionFailure>) -> Self {
+        Self {
+            failures,
+            scroll: 0,
+        }
+    }
+
+    pub fn failures(&self) -> &[DeletionFailure] {
+        &self.failures
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.scroll + 1 < self.failures.len() {
+            self.scroll += 1;
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+}
@@ -0,0 +1,37 @@
+use crate::backend::{deletion::DeletionProgress, entry_size::EntrySize};
+
+pub struct DeletionProgressPopup {
+    progress: DeletionProgress,
+    total: EntrySize,
+    cancelling: bool,
+}
+
+impl DeletionProgressPopup {
+    pub fn new(total: EntrySize) -> Self {
+        Self {
+            progress: DeletionProgress::default(),
+            total,
+            cancelling: false,
+        }
+    }
+
+    pub fn progress(&self) -> DeletionProgress {
+        self.progress
+    }
+
+    pub fn total(&self) -> EntrySize {
+        self.total
+    }
+
+    pub fn cancelling(&self) -> bool {
+        self.cancelling
+    }
+
+    pub fn update(&mut self, progress: DeletionProgress) {
+        self.progress = progress;
+    }
+
+    pub fn cancel(&mut self) {
+        self.cancelling = true;
+    }
+}
@@ -0,0 +1,86 @@
+use crate::backend::{
+    entry_node::EntryNodeView,
+    name_pattern::{NamePattern, PatternKind},
+};
+
+/// Narrows the entries of the current directory down to those whose name
+/// matches the typed pattern.
+#[derive(Default)]
+pub struct Filter {
+    input: String,
+    kind: PatternKind,
+    /// The compiled input, `None` if the input is empty or invalid.
+    pattern: Option<NamePattern>,
+    error: Option<String>,
+    matched: usize,
+    total: usize,
+}
+
+impl Filter {
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn kind(&self) -> PatternKind {
+        self.kind
+    }
+
+    /// Why the input could not be compiled.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.pattern.is_some()
+    }
+
+    /// The number of matching entries and of all entries seen by the last
+    /// [`Filter::apply`].
+    pub fn counts(&self) -> (usize, usize) {
+        (self.matched, self.total)
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.input.push(c);
+        self.compile();
+    }
+
+    pub fn pop(&mut self) {
+        self.input.pop();
+        self.compile();
+    }
+
+    pub fn switch_kind(&mut self) {
+        self.kind = self.kind.next();
+        self.compile();
+    }
+
+    /// Forget the input, keeping the kind for the next filter.
+    pub fn clear(&mut self) {
+        self.input.clear();
+        self.compile();
+    }
+
+    /// Keep only the matching entries. All entries are kept while the input
+    /// is invalid, so that the listing does not flicker while typing.
+    pub fn apply(&mut self, mut entries: Vec<EntryNodeView>) -> Vec<EntryNodeView> {
+        self.total = entries.len();
+        if let Some(pattern) = &self.pattern {
+            entries.retain(|entry| pattern.is_match(&entry.name));
+        }
+        self.matched = entries.len();
+        entries
+    }
+
+    fn compile(&mut self) {
+        self.error = None;
+        self.pattern = None;
+        if self.input.is_empty() {
+            return;
+        }
+        match NamePattern::new(self.kind, &self.input) {
+            Ok(pattern) => self.pattern = Some(pattern),
+            Err(error) => self.error = Some(format!("{error:#}")),
+        }
+    }
+}
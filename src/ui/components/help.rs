@@ -0,0 +1,75 @@
+use crate::ui::{
+    app::AppFocus,
+    key_map::{self, KeyMap},
+};
+
+/// An action listed in the help, with its keys.
+pub struct HelpEntry {
+    /// The keys of the action separated by commas.
+    pub keys: String,
+    pub description: &'static str,
+    /// The name of the action in the config file.
+    pub name: &'static str,
+}
+
+/// The actions of the screen the help was opened on, listed from its key map
+/// so that the help always shows the keys which are actually bound.
+pub struct HelpPopup {
+    previous: Box<AppFocus>,
+    entries: Vec<HelpEntry>,
+    scroll: usize,
+}
+
+impl HelpPopup {
+    pub fn new(previous: AppFocus, key_map: &KeyMap) -> Self {
+        let entries = key_map
+            .actions()
+            .into_iter()
+            .map(|(action, keys)| HelpEntry {
+                keys: keys
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                description: key_map::action_description(action),
+                name: key_map::action_name(action),
+            })
+            .collect();
+        Self {
+            previous: Box::new(previous),
+            entries,
+            scroll: 0,
+        }
+    }
+
+    /// The screen the help was opened on.
+    pub fn previous(&self) -> &AppFocus {
+        &self.previous
+    }
+
+    pub fn previous_mut(&mut self) -> &mut AppFocus {
+        &mut self.previous
+    }
+
+    pub fn into_previous(self) -> AppFocus {
+        *self.previous
+    }
+
+    pub fn entries(&self) -> &[HelpEntry] {
+        &self.entries
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn scroll_down(&mut self) {
+        if self.scroll + 1 < self.entries.len() {
+            self.scroll += 1;
+        }
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll = self.scroll.saturating_sub(1);
+    }
+}
@@ -1,3 +1,9 @@
 pub mod confirm_delete;
+pub mod deletion_errors;
+pub mod deletion_progress;
+pub mod filter;
+pub mod help;
 pub mod indicator;
+pub mod screen_areas;
+pub mod search;
 pub mod table;
@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+
+use ratatui::layout::Rect;
+
+/// Where the clickable parts of the user interface were last rendered, so that
+/// mouse events can be mapped to them.
+#[derive(Default)]
+pub struct ScreenAreas {
+    /// The rows of the main table.
+    pub main_table: Option<Rect>,
+    /// The rows of the preview table.
+    pub preview_table: Option<Rect>,
+    /// The ancestors of the current directory in the top panel.
+    pub breadcrumbs: Vec<(Rect, PathBuf)>,
+    /// The yes and no buttons of the confirm delete popup.
+    pub confirm_delete_buttons: Option<(Rect, Rect)>,
+}
+
+impl ScreenAreas {
+    /// The ancestor of the current directory under the pointer.
+    pub fn breadcrumb_at(&self, column: u16, row: u16) -> Option<&Path> {
+        self.breadcrumbs
+            .iter()
+            .find(|(area, _)| contains(*area, column, row))
+            .map(|(_, path)| path.as_path())
+    }
+
+    /// The visible row of the main table under the pointer, counted from the
+    /// first visible one.
+    pub fn main_table_row_at(&self, column: u16, row: u16) -> Option<usize> {
+        self.main_table
+            .filter(|area| contains(*area, column, row))
+            .map(|area| usize::from(row - area.y))
+    }
+
+    pub fn is_over_preview_table(&self, column: u16, row: u16) -> bool {
+        self.preview_table
+            .is_some_and(|area| contains(area, column, row))
+    }
+
+    /// Whether the pointer is over the yes button, `None` if it is over
+    /// neither of the buttons.
+    pub fn confirm_delete_button_at(&self, column: u16, row: u16) -> Option<bool> {
+        let (yes, no) = self.confirm_delete_buttons?;
+        if contains(yes, column, row) {
+            Some(true)
+        } else if contains(no, column, row) {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    (area.left()..area.right()).contains(&column) && (area.top()..area.bottom()).contains(&row)
+}
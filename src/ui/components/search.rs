@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use crate::backend::{entry_node::EntryNodeView, name_pattern::PatternKind};
+
+use super::table::StatefulTable;
+
+/// The query being typed, kept between searches.
+#[derive(Default)]
+pub struct SearchInput {
+    input: String,
+    kind: PatternKind,
+    /// Why the last query could not be parsed.
+    error: Option<String>,
+}
+
+impl SearchInput {
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    pub fn kind(&self) -> PatternKind {
+        self.kind
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.input.push(c);
+        self.error = None;
+    }
+
+    pub fn pop(&mut self) {
+        self.input.pop();
+        self.error = None;
+    }
+
+    pub fn switch_kind(&mut self) {
+        self.kind = self.kind.next();
+        self.error = None;
+    }
+}
+
+/// The entries matching a query, the largest first.
+pub struct SearchResults {
+    pub query: String,
+    /// The root of the tree, the paths are shown relative to it.
+    pub root: PathBuf,
+    pub table: StatefulTable<EntryNodeView>,
+}
+
+impl SearchResults {
+    pub fn new(query: String, root: PathBuf, entries: Vec<EntryNodeView>) -> Self {
+        let focused = (!entries.is_empty()).then_some(0);
+        Self {
+            query,
+            root,
+            table: StatefulTable::with_focused(entries, focused),
+        }
+    }
+}
@@ -86,6 +86,30 @@ impl<T> StatefulTable<T> {
         self.state.select(Some(i));
     }
 
+    /// Focus the next item, staying on the last one instead of wrapping around.
+    pub fn focus_next_without_wrapping(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+
+        let i = match self.state.selected() {
+            Some(i) => (i + 1).min(self.items.len() - 1),
+            None => 0,
+        };
+        self.state.select(Some(i));
+    }
+
+    /// Focus the previous item, staying on the first one instead of wrapping
+    /// around.
+    pub fn focus_previous_without_wrapping(&mut self) {
+        if self.items.is_empty() {
+            return;
+        }
+
+        let i = self.state.selected().map_or(0, |i| i.saturating_sub(1));
+        self.state.select(Some(i));
+    }
+
     pub fn focus_first(&mut self) {
         if self.items.is_empty() {
             return;
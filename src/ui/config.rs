@@ -0,0 +1,138 @@
+//! The configuration file, read from `$XDG_CONFIG_HOME/diskostat/config.toml`.
+//!
+//! Every section of `keys` binds the actions of one screen to lists of keys,
+//! replacing the default keys of the actions it names:
+//!
+//! ```toml
+//! [keys.main_screen]
+//! quit = ["q", "ctrl-q"]
+//! focus_first_item = "g g"
+//! ```
+//!
+//! The command opening files, `xdg-open` unless set:
+//!
+//! ```toml
+//! opener = "gio open"
+//! ```
+//!
+//! Themes are defined in the sections of `themes`, each changing some colors
+//! of a built-in theme:
+//!
+//! ```toml
+//! [themes.solarized]
+//! base = "light"
+//! primary = "#268bd2"
+//! secondary = 245
+//! ```
+
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The theme used unless one is given on the command line.
+    pub theme: Option<String>,
+    pub themes: BTreeMap<String, ThemeConfig>,
+    /// The command opening files with their default application, followed
+    /// by the path of the file.
+    pub opener: Option<String>,
+    pub keys: KeysConfig,
+}
+
+/// The colors of a theme which differ from its base theme.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// The built-in theme providing the other colors. Themes named after a
+    /// built-in theme are based on it, other themes on the dark theme.
+    pub base: Option<String>,
+    pub primary: Option<ColorValue>,
+    pub secondary: Option<ColorValue>,
+    pub tertiary: Option<ColorValue>,
+    pub fg: Option<ColorValue>,
+    pub primary_bg: Option<ColorValue>,
+    pub secondary_bg: Option<ColorValue>,
+    pub highlight: Option<ColorValue>,
+    pub colorful_bar: Option<bool>,
+    pub added: Option<ColorValue>,
+    pub removed: Option<ColorValue>,
+    pub grown: Option<ColorValue>,
+    pub shrunk: Option<ColorValue>,
+}
+
+/// A color name, a 256-color index or a hex color.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ColorValue {
+    Name(String),
+    Index(u8),
+}
+
+/// The keys of the actions by their names, for each screen.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysConfig {
+    pub main_screen: BTreeMap<String, Keys>,
+    pub confirm_delete_popup: BTreeMap<String, Keys>,
+    pub deletion_progress_popup: BTreeMap<String, Keys>,
+    pub deletion_errors_popup: BTreeMap<String, Keys>,
+    pub traversal_errors: BTreeMap<String, Keys>,
+    pub filter: BTreeMap<String, Keys>,
+    pub search: BTreeMap<String, Keys>,
+    pub search_results: BTreeMap<String, Keys>,
+    pub help_popup: BTreeMap<String, Keys>,
+}
+
+/// A single key or a list of keys, each key either a single key press like
+/// `ctrl-d` or a sequence of key presses separated by spaces like `g g`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Keys {
+    pub fn as_slice(&self) -> &[String] {
+        match self {
+            Keys::One(key) => std::slice::from_ref(key),
+            Keys::Many(keys) => keys,
+        }
+    }
+}
+
+/// The opener used unless the config sets one.
+const DEFAULT_OPENER: &str = if cfg!(target_os = "macos") {
+    "open"
+} else {
+    "xdg-open"
+};
+
+impl Config {
+    /// The path of the config file, `None` if there is no config directory.
+    pub fn path() -> Option<PathBuf> {
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            // Relative paths are invalid and should be ignored.
+            .filter(|path| path.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("diskostat").join("config.toml"))
+    }
+
+    pub fn opener(&self) -> &str {
+        self.opener.as_deref().unwrap_or(DEFAULT_OPENER)
+    }
+
+    /// Read the config file, the defaults are used if there is none.
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path().filter(|path| path.exists()) else {
+            return Ok(Self::default());
+        };
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Invalid config {}", path.display()))
+    }
+}
@@ -1,100 +1,23 @@
-use std::collections::HashMap;
-
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 
 use super::{
     app::{Action, AppFocus},
     event_handling::DiskoEvent,
+    key_map::{KeyMap, KeyMaps, KeyPress, KeySequence},
 };
 
-const SINGLE_KEY_COMMANDS_MAIN_SCREEN_COUNT: usize = 15;
-const SINGLE_KEY_COMMANDS_CONFIRM_DELETE_POPUP_COUNT: usize = 9;
-const MULTI_KEY_COMMANDS_COUNT: usize = 1;
-
-const DEFAULT_SINGLE_KEY_COMMANDS_MAIN_SCREEN: [(KeyCode, Action);
-    SINGLE_KEY_COMMANDS_MAIN_SCREEN_COUNT] = [
-    (KeyCode::Esc, Action::Quit),
-    (KeyCode::Char('q'), Action::Quit),
-    (KeyCode::Char('s'), Action::ToggleSelection),
-    (KeyCode::Char('d'), Action::ShowConfirmDeletePopup),
-    (KeyCode::Down, Action::FocusNextItem),
-    (KeyCode::Char('j'), Action::FocusNextItem),
-    (KeyCode::Up, Action::FocusPreviousItem),
-    (KeyCode::Char('k'), Action::FocusPreviousItem),
-    (KeyCode::Char('G'), Action::FocusLastItem),
-    (KeyCode::Right, Action::EnterFocusedDirectory),
-    (KeyCode::Char('l'), Action::EnterFocusedDirectory),
-    (KeyCode::Left, Action::EnterParentDirectory),
-    (KeyCode::Char('h'), Action::EnterParentDirectory),
-    (KeyCode::Char('a'), Action::SwitchEntryDisplaySize),
-    (KeyCode::Char('b'), Action::SwitchProgress),
-];
-
-const DEFAULT_SINGLE_KEY_COMMANDS_CONFIRM_DELETE_POPUP: [(KeyCode, Action);
-    SINGLE_KEY_COMMANDS_CONFIRM_DELETE_POPUP_COUNT] = [
-    (KeyCode::Esc, Action::ShowMainScreen),
-    (KeyCode::Char('q'), Action::ShowMainScreen),
-    (KeyCode::Char('n'), Action::ShowMainScreen),
-    (KeyCode::Right, Action::DeletePopupSwitchConfirmation),
-    (KeyCode::Left, Action::DeletePopupSwitchConfirmation),
-    (KeyCode::Char('l'), Action::DeletePopupSwitchConfirmation),
-    (KeyCode::Char('h'), Action::DeletePopupSwitchConfirmation),
-    (KeyCode::Enter, Action::DeletePopupSelect),
-    (KeyCode::Char('y'), Action::ConfirmDelete),
-];
-
-const DEFAULT_MULTI_KEY_COMMANDS: [(&str, Action); MULTI_KEY_COMMANDS_COUNT] =
-    [("gg", Action::FocusFirstItem(String::new()))];
-
+#[derive(Default)]
 pub struct DiskoEventHandler {
-    buffer: Vec<char>,
-    single_key_commands_main_screen: HashMap<KeyCode, Action>,
-    single_key_commands_confirm_delete_popup: HashMap<KeyCode, Action>,
-    multi_key_commands: HashMap<String, Action>,
-}
-
-impl Default for DiskoEventHandler {
-    fn default() -> Self {
-        let single_key_commands_main_screen =
-            HashMap::from(DEFAULT_SINGLE_KEY_COMMANDS_MAIN_SCREEN);
-
-        let single_key_commands_confirm_delete_popup =
-            HashMap::from(DEFAULT_SINGLE_KEY_COMMANDS_CONFIRM_DELETE_POPUP);
-
-        let multi_key_commands = HashMap::from(
-            DEFAULT_MULTI_KEY_COMMANDS.map(|(command, action)| (command.to_string(), action)),
-        );
-
-        Self {
-            buffer: Vec::new(),
-            single_key_commands_main_screen,
-            single_key_commands_confirm_delete_popup,
-            multi_key_commands,
-        }
-    }
+    /// The key presses of a sequence typed so far.
+    buffer: Vec<KeyPress>,
+    key_maps: KeyMaps,
 }
 
 impl DiskoEventHandler {
-    pub fn new(
-        single_key_commands_main_screen: [(KeyCode, Action); SINGLE_KEY_COMMANDS_MAIN_SCREEN_COUNT],
-        single_key_commands_confirm_delete_popup: [(KeyCode, Action);
-            SINGLE_KEY_COMMANDS_CONFIRM_DELETE_POPUP_COUNT],
-        multi_key_commands: [(&str, Action); MULTI_KEY_COMMANDS_COUNT],
-    ) -> Self {
-        let single_key_commands_main_screen = HashMap::from(single_key_commands_main_screen);
-
-        let single_key_commands_confirm_delete_popup =
-            HashMap::from(single_key_commands_confirm_delete_popup);
-
-        let multi_key_commands = HashMap::from(
-            multi_key_commands.map(|(command, action)| (command.to_string(), action)),
-        );
-
+    pub fn new(key_maps: KeyMaps) -> Self {
         Self {
             buffer: Vec::new(),
-            single_key_commands_main_screen,
-            single_key_commands_confirm_delete_popup,
-            multi_key_commands,
+            key_maps,
         }
     }
 
@@ -104,91 +27,129 @@ impl DiskoEventHandler {
         match event {
             DiskoEvent::Tick => Some(Action::Tick),
             DiskoEvent::TraversalFinished => Some(Action::SetTraversalFinished),
+            DiskoEvent::DeletionProgress(progress) => {
+                Some(Action::UpdateDeletionProgress(progress))
+            }
+            DiskoEvent::DeletionFinished => Some(Action::FinishDeletion),
+            DiskoEvent::DuplicatesFound => Some(Action::FinishDuplicateSearch),
+            DiskoEvent::FileSystemChanged => Some(Action::ApplyWatchedChanges),
             DiskoEvent::Resize(w, h) => Some(Action::Resize(w, h)),
             DiskoEvent::Key(key) => match key.code {
                 KeyCode::Char('c' | 'C') if key.modifiers == KeyModifiers::CONTROL => {
                     Some(Action::Quit)
                 }
                 _ => match focus {
-                    AppFocus::MainScreen => self.handle_key_events_main_screen(key),
-                    AppFocus::ConfirmDeletePopup(_) => {
-                        self.handle_key_events_confirm_delete_popup(key)
+                    // The buffered key presses belong to the main screen.
+                    AppFocus::MainScreen | AppFocus::BufferingInput => {
+                        self.handle_key_events_main_screen(key)
                     }
-                    AppFocus::BufferingInput => self.handle_key_events_buffering_input(key),
+                    AppFocus::Filter => self.handle_key_events_filter(key),
+                    AppFocus::Search => self.handle_key_events_search(key),
+                    _ => Self::handle_key_events(self.key_map(focus), key),
                 },
             },
+            DiskoEvent::Mouse(mouse) => self.handle_mouse_events(mouse, focus),
             _ => None,
         }
     }
 
-    fn handle_key_events_main_screen(&mut self, key: KeyEvent) -> Option<Action> {
-        let action = match key.modifiers {
-            // SHIFT is needed to capture capitalized characters
-            KeyModifiers::NONE | KeyModifiers::SHIFT => {
-                self.single_key_commands_main_screen.get(&key.code)
-            }
-            // Other modifiers are ignored
-            _ => return None,
-        };
-
-        // If a single key command is pressed, return the action
-        if action.is_some() {
-            return action.cloned();
+    /// The key map used on the screen.
+    pub fn key_map(&self, focus: &AppFocus) -> &KeyMap {
+        match focus {
+            AppFocus::MainScreen | AppFocus::BufferingInput => &self.key_maps.main_screen,
+            AppFocus::ConfirmDeletePopup(_) => &self.key_maps.confirm_delete_popup,
+            AppFocus::DeletionProgressPopup(_) => &self.key_maps.deletion_progress_popup,
+            AppFocus::DeletionErrorsPopup(_) => &self.key_maps.deletion_errors_popup,
+            AppFocus::TraversalErrors(_) => &self.key_maps.traversal_errors,
+            AppFocus::Filter => &self.key_maps.filter,
+            AppFocus::Search => &self.key_maps.search,
+            AppFocus::SearchResults(_) => &self.key_maps.search_results,
+            AppFocus::Help(_) => &self.key_maps.help_popup,
         }
-
-        self.handle_key_events_buffering_input(key)
     }
 
-    fn handle_key_events_confirm_delete_popup(&self, key: KeyEvent) -> Option<Action> {
-        match key.modifiers {
-            // SHIFT is needed to capture capitalized characters
-            // Only handle single key commands
-            KeyModifiers::NONE | KeyModifiers::SHIFT => self
-                .single_key_commands_confirm_delete_popup
-                .get(&key.code)
-                .cloned(),
-            // Other modifiers are ignored
+    fn handle_mouse_events(&self, mouse: MouseEvent, focus: &AppFocus) -> Option<Action> {
+        let (column, row) = (mouse.column, mouse.row);
+        match (mouse.kind, focus) {
+            (
+                MouseEventKind::Down(MouseButton::Left),
+                AppFocus::MainScreen | AppFocus::ConfirmDeletePopup(_),
+            ) => Some(Action::Click { column, row }),
+            // The pointer decides whether the main or the preview table scrolls.
+            (MouseEventKind::ScrollDown, AppFocus::MainScreen) => {
+                Some(Action::ScrollWheelDown { column, row })
+            }
+            (MouseEventKind::ScrollUp, AppFocus::MainScreen) => {
+                Some(Action::ScrollWheelUp { column, row })
+            }
+            (
+                MouseEventKind::ScrollDown,
+                AppFocus::DeletionErrorsPopup(_)
+                | AppFocus::TraversalErrors(_)
+                | AppFocus::SearchResults(_)
+                | AppFocus::Help(_),
+            ) => Some(Action::ScrollDown),
+            (
+                MouseEventKind::ScrollUp,
+                AppFocus::DeletionErrorsPopup(_)
+                | AppFocus::TraversalErrors(_)
+                | AppFocus::SearchResults(_)
+                | AppFocus::Help(_),
+            ) => Some(Action::ScrollUp),
+            // Moving and dragging the pointer is ignored.
             _ => None,
         }
     }
 
-    fn handle_key_events_buffering_input(&mut self, key: KeyEvent) -> Option<Action> {
-        // SHIFT is needed to capture capitalized characters
-        if matches!(key.modifiers, KeyModifiers::NONE | KeyModifiers::SHIFT) {
-            if let KeyCode::Char(c) = key.code {
-                return self.handle_buffered_input(c);
+    fn handle_key_events_main_screen(&mut self, key: KeyEvent) -> Option<Action> {
+        self.buffer.push(KeyPress::from(key));
+        let main_screen = &self.key_maps.main_screen;
+
+        if let Some(action) = main_screen.get(&self.buffer) {
+            let action = action.clone();
+            let presses = std::mem::take(&mut self.buffer);
+            if presses.len() == 1 {
+                return Some(action);
             }
+            let input = KeySequence::from(presses).to_string();
+            return Some(Action::FinishInput(input, Box::new(action)));
         }
 
-        // If any other modifier is used or a non-char key is pressed, clear the buffer
+        // The key presses so far may be the start of a sequence.
+        let input = KeySequence::from(self.buffer.clone()).to_string();
+        if main_screen.starts_sequence(&self.buffer) {
+            return Some(Action::BufferInput(input));
+        }
         self.buffer.clear();
-        Some(Action::ShowMainScreen)
+        Some(Action::InvalidInput(input))
     }
 
-    pub fn handle_buffered_input(&mut self, char: char) -> Option<Action> {
-        self.buffer.push(char);
-        let buffer_content = self.buffer.iter().collect::<String>();
+    /// Only handle the keys bound in the key map.
+    fn handle_key_events(key_map: &KeyMap, key: KeyEvent) -> Option<Action> {
+        key_map.get(&[KeyPress::from(key)]).cloned()
+    }
 
-        // If the buffer content is not a prefix of any multi key command, it is an invalid command
-        if !self
-            .multi_key_commands
-            .keys()
-            .any(|command| command.starts_with(&buffer_content))
-        {
-            self.buffer.clear();
-            return Some(Action::InvalidInput(buffer_content));
+    fn handle_key_events_filter(&self, key: KeyEvent) -> Option<Action> {
+        match key.code {
+            // SHIFT is needed to capture capitalized characters
+            KeyCode::Char(c)
+                if matches!(key.modifiers, KeyModifiers::NONE | KeyModifiers::SHIFT) =>
+            {
+                Some(Action::FilterInput(c))
+            }
+            _ => Self::handle_key_events(&self.key_maps.filter, key),
         }
+    }
 
-        match self.multi_key_commands.get(&buffer_content) {
-            Some(action) => {
-                self.buffer.clear();
-                match action {
-                    Action::FocusFirstItem(_) => Some(Action::FocusFirstItem(buffer_content)),
-                    _ => None,
-                }
+    fn handle_key_events_search(&self, key: KeyEvent) -> Option<Action> {
+        match key.code {
+            // SHIFT is needed to capture capitalized characters
+            KeyCode::Char(c)
+                if matches!(key.modifiers, KeyModifiers::NONE | KeyModifiers::SHIFT) =>
+            {
+                Some(Action::SearchInput(c))
             }
-            // The buffer content is definitely a prefix of a multi key command
-            None => Some(Action::BufferInput(buffer_content)),
+            _ => Self::handle_key_events(&self.key_maps.search, key),
         }
     }
 }
@@ -1,6 +1,8 @@
 use anyhow::Result;
 use crossterm::event::{Event as CrosstermEvent, KeyEvent, KeyEventKind, MouseEvent};
 
+use crate::backend::deletion::DeletionProgress;
+
 use std::{
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -21,6 +23,14 @@ pub enum DiskoEvent {
     Tick,
     /// The traversal has finished.
     TraversalFinished,
+    /// Entries have been removed by the running deletion.
+    DeletionProgress(DeletionProgress),
+    /// The deletion has finished or has been cancelled.
+    DeletionFinished,
+    /// The search for duplicate files has finished or has been cancelled.
+    DuplicatesFound,
+    /// Entries in the watched directories have changed.
+    FileSystemChanged,
     /// Key press.
     Key(KeyEvent),
     /// Mouse click/scroll.
@@ -70,6 +80,8 @@ impl EventHandler {
 
     /// Starts the processing of events.
     pub fn start(&mut self) -> Result<()> {
+        // The handler may be started again after being stopped.
+        self.should_cancel.store(false, Ordering::SeqCst);
         self.sender
             .send(DiskoEvent::Init)
             .expect("Failed to send init event");
@@ -0,0 +1,743 @@
+use std::{fmt, mem, str::FromStr};
+
+use anyhow::{bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use super::{app::Action, config::KeysConfig};
+
+const SINGLE_KEY_COMMANDS_MAIN_SCREEN_COUNT: usize = 31;
+const SINGLE_KEY_COMMANDS_CONFIRM_DELETE_POPUP_COUNT: usize = 10;
+const SINGLE_KEY_COMMANDS_DELETION_PROGRESS_POPUP_COUNT: usize = 3;
+const SINGLE_KEY_COMMANDS_DELETION_ERRORS_POPUP_COUNT: usize = 8;
+const SINGLE_KEY_COMMANDS_TRAVERSAL_ERRORS_COUNT: usize = 8;
+const SINGLE_KEY_COMMANDS_FILTER_COUNT: usize = 6;
+const SINGLE_KEY_COMMANDS_SEARCH_COUNT: usize = 4;
+const SINGLE_KEY_COMMANDS_SEARCH_RESULTS_COUNT: usize = 10;
+const SINGLE_KEY_COMMANDS_HELP_POPUP_COUNT: usize = 7;
+const MULTI_KEY_COMMANDS_COUNT: usize = 1;
+const BINDABLE_ACTIONS_COUNT: usize = 44;
+
+const DEFAULT_SINGLE_KEY_COMMANDS_MAIN_SCREEN: [(&str, Action);
+    SINGLE_KEY_COMMANDS_MAIN_SCREEN_COUNT] = [
+    ("esc", Action::ClearFilterOrQuit),
+    ("q", Action::Quit),
+    ("?", Action::ShowHelp),
+    ("s", Action::ToggleSelection),
+    ("d", Action::ShowConfirmDeletePopup),
+    ("u", Action::UndoDeletion),
+    ("E", Action::ShowTraversalErrors),
+    ("/", Action::ShowFilter),
+    ("n", Action::FocusNextMatch),
+    ("N", Action::FocusPreviousMatch),
+    ("f", Action::ShowSearch),
+    ("L", Action::ToggleLargestFiles),
+    ("D", Action::FindDuplicates),
+    ("r", Action::RescanFocusedDirectory),
+    ("R", Action::RescanRoot),
+    ("o", Action::OpenFocusedEntry),
+    ("e", Action::EditFocusedFile),
+    ("S", Action::OpenShell),
+    ("down", Action::FocusNextItem),
+    ("j", Action::FocusNextItem),
+    ("up", Action::FocusPreviousItem),
+    ("k", Action::FocusPreviousItem),
+    ("G", Action::FocusLastItem),
+    ("right", Action::EnterFocusedDirectory),
+    ("l", Action::EnterFocusedDirectory),
+    ("left", Action::EnterParentDirectory),
+    ("h", Action::EnterParentDirectory),
+    ("a", Action::SwitchEntryDisplaySize),
+    ("c", Action::CycleSortMode),
+    ("i", Action::ToggleSortDirection),
+    ("b", Action::SwitchProgress),
+];
+
+const DEFAULT_SINGLE_KEY_COMMANDS_CONFIRM_DELETE_POPUP: [(&str, Action);
+    SINGLE_KEY_COMMANDS_CONFIRM_DELETE_POPUP_COUNT] = [
+    ("esc", Action::ShowMainScreen),
+    ("q", Action::ShowMainScreen),
+    ("n", Action::ShowMainScreen),
+    ("right", Action::DeletePopupSwitchConfirmation),
+    ("left", Action::DeletePopupSwitchConfirmation),
+    ("l", Action::DeletePopupSwitchConfirmation),
+    ("h", Action::DeletePopupSwitchConfirmation),
+    ("enter", Action::DeletePopupSelect),
+    ("y", Action::ConfirmDelete),
+    ("?", Action::ShowHelp),
+];
+
+const DEFAULT_SINGLE_KEY_COMMANDS_DELETION_PROGRESS_POPUP: [(&str, Action);
+    SINGLE_KEY_COMMANDS_DELETION_PROGRESS_POPUP_COUNT] = [
+    ("esc", Action::CancelDeletion),
+    ("q", Action::CancelDeletion),
+    ("c", Action::CancelDeletion),
+];
+
+const DEFAULT_SINGLE_KEY_COMMANDS_DELETION_ERRORS_POPUP: [(&str, Action);
+    SINGLE_KEY_COMMANDS_DELETION_ERRORS_POPUP_COUNT] = [
+    ("esc", Action::ShowMainScreen),
+    ("q", Action::ShowMainScreen),
+    ("enter", Action::ShowMainScreen),
+    ("?", Action::ShowHelp),
+    ("down", Action::ScrollDown),
+    ("j", Action::ScrollDown),
+    ("up", Action::ScrollUp),
+    ("k", Action::ScrollUp),
+];
+
+const DEFAULT_SINGLE_KEY_COMMANDS_TRAVERSAL_ERRORS: [(&str, Action);
+    SINGLE_KEY_COMMANDS_TRAVERSAL_ERRORS_COUNT] = [
+    ("esc", Action::ShowMainScreen),
+    ("q", Action::ShowMainScreen),
+    ("E", Action::ShowMainScreen),
+    ("?", Action::ShowHelp),
+    ("down", Action::ScrollDown),
+    ("j", Action::ScrollDown),
+    ("up", Action::ScrollUp),
+    ("k", Action::ScrollUp),
+];
+
+/// Typed characters are handled separately, they always extend the filter.
+const DEFAULT_SINGLE_KEY_COMMANDS_FILTER: [(&str, Action); SINGLE_KEY_COMMANDS_FILTER_COUNT] = [
+    ("esc", Action::ClearFilter),
+    ("enter", Action::ApplyFilter),
+    ("backspace", Action::FilterDeleteChar),
+    ("tab", Action::FilterSwitchKind),
+    ("down", Action::FocusNextItem),
+    ("up", Action::FocusPreviousItem),
+];
+
+/// Typed characters are handled separately, they always extend the query.
+const DEFAULT_SINGLE_KEY_COMMANDS_SEARCH: [(&str, Action); SINGLE_KEY_COMMANDS_SEARCH_COUNT] = [
+    ("esc", Action::ShowMainScreen),
+    ("enter", Action::RunSearch),
+    ("backspace", Action::SearchDeleteChar),
+    ("tab", Action::SearchSwitchKind),
+];
+
+const DEFAULT_SINGLE_KEY_COMMANDS_SEARCH_RESULTS: [(&str, Action);
+    SINGLE_KEY_COMMANDS_SEARCH_RESULTS_COUNT] = [
+    ("esc", Action::ShowMainScreen),
+    ("q", Action::ShowMainScreen),
+    ("f", Action::ShowSearch),
+    ("enter", Action::JumpToSearchResult),
+    ("l", Action::JumpToSearchResult),
+    ("?", Action::ShowHelp),
+    ("down", Action::ScrollDown),
+    ("j", Action::ScrollDown),
+    ("up", Action::ScrollUp),
+    ("k", Action::ScrollUp),
+];
+
+const DEFAULT_SINGLE_KEY_COMMANDS_HELP_POPUP: [(&str, Action);
+    SINGLE_KEY_COMMANDS_HELP_POPUP_COUNT] = [
+    ("esc", Action::CloseHelp),
+    ("q", Action::CloseHelp),
+    ("?", Action::CloseHelp),
+    ("down", Action::ScrollDown),
+    ("j", Action::ScrollDown),
+    ("up", Action::ScrollUp),
+    ("k", Action::ScrollUp),
+];
+
+/// The key presses of a sequence are separated by spaces.
+const DEFAULT_MULTI_KEY_COMMANDS: [(&str, Action); MULTI_KEY_COMMANDS_COUNT] =
+    [("g g", Action::FocusFirstItem)];
+
+/// The actions which can be bound to keys, by their names in the config file,
+/// with their descriptions.
+const BINDABLE_ACTIONS: [(&str, Action, &str); BINDABLE_ACTIONS_COUNT] = [
+    ("quit", Action::Quit, "Quit"),
+    (
+        "clear_filter_or_quit",
+        Action::ClearFilterOrQuit,
+        "Clear the filter, quit if there is none",
+    ),
+    ("show_main_screen", Action::ShowMainScreen, "Close"),
+    (
+        "focus_next_item",
+        Action::FocusNextItem,
+        "Focus the next entry",
+    ),
+    (
+        "focus_previous_item",
+        Action::FocusPreviousItem,
+        "Focus the previous entry",
+    ),
+    (
+        "focus_first_item",
+        Action::FocusFirstItem,
+        "Focus the first entry",
+    ),
+    (
+        "focus_last_item",
+        Action::FocusLastItem,
+        "Focus the last entry",
+    ),
+    (
+        "enter_focused_directory",
+        Action::EnterFocusedDirectory,
+        "Enter the focused directory",
+    ),
+    (
+        "enter_parent_directory",
+        Action::EnterParentDirectory,
+        "Go to the parent directory",
+    ),
+    (
+        "toggle_selection",
+        Action::ToggleSelection,
+        "Select or unselect the focused entry",
+    ),
+    (
+        "show_confirm_delete_popup",
+        Action::ShowConfirmDeletePopup,
+        "Delete the selected entries or the focused one",
+    ),
+    (
+        "delete_popup_switch_confirmation",
+        Action::DeletePopupSwitchConfirmation,
+        "Switch between yes and no",
+    ),
+    (
+        "delete_popup_select",
+        Action::DeletePopupSelect,
+        "Answer with the highlighted choice",
+    ),
+    ("confirm_delete", Action::ConfirmDelete, "Delete"),
+    (
+        "cancel_deletion",
+        Action::CancelDeletion,
+        "Cancel the deletion",
+    ),
+    (
+        "undo_deletion",
+        Action::UndoDeletion,
+        "Undo the last deletion",
+    ),
+    ("scroll_down", Action::ScrollDown, "Scroll down"),
+    ("scroll_up", Action::ScrollUp, "Scroll up"),
+    (
+        "show_traversal_errors",
+        Action::ShowTraversalErrors,
+        "Show the entries which could not be read",
+    ),
+    (
+        "show_filter",
+        Action::ShowFilter,
+        "Filter the entries by name",
+    ),
+    (
+        "filter_delete_char",
+        Action::FilterDeleteChar,
+        "Delete the last character",
+    ),
+    (
+        "filter_switch_kind",
+        Action::FilterSwitchKind,
+        "Switch between substring, glob and regex",
+    ),
+    ("apply_filter", Action::ApplyFilter, "Keep the filter"),
+    ("clear_filter", Action::ClearFilter, "Clear the filter"),
+    (
+        "focus_next_match",
+        Action::FocusNextMatch,
+        "Focus the next entry matching the filter",
+    ),
+    (
+        "focus_previous_match",
+        Action::FocusPreviousMatch,
+        "Focus the previous entry matching the filter",
+    ),
+    (
+        "show_search",
+        Action::ShowSearch,
+        "Search the whole tree by name",
+    ),
+    (
+        "search_delete_char",
+        Action::SearchDeleteChar,
+        "Delete the last character",
+    ),
+    (
+        "search_switch_kind",
+        Action::SearchSwitchKind,
+        "Switch between substring, glob and regex",
+    ),
+    ("run_search", Action::RunSearch, "Search"),
+    (
+        "jump_to_search_result",
+        Action::JumpToSearchResult,
+        "Show the focused result in its directory",
+    ),
+    (
+        "toggle_largest_files",
+        Action::ToggleLargestFiles,
+        "List the largest files of the tree",
+    ),
+    (
+        "find_duplicates",
+        Action::FindDuplicates,
+        "Find files with the same contents",
+    ),
+    (
+        "rescan_focused_directory",
+        Action::RescanFocusedDirectory,
+        "Rescan the focused directory",
+    ),
+    ("rescan_root", Action::RescanRoot, "Rescan the whole tree"),
+    (
+        "switch_entry_display_size",
+        Action::SwitchEntryDisplaySize,
+        "Switch between apparent size, disk size and inodes",
+    ),
+    (
+        "cycle_sort_mode",
+        Action::CycleSortMode,
+        "Sort by the next key",
+    ),
+    (
+        "toggle_sort_direction",
+        Action::ToggleSortDirection,
+        "Reverse the order",
+    ),
+    (
+        "switch_progress",
+        Action::SwitchProgress,
+        "Switch between bars and percentages",
+    ),
+    (
+        "show_help",
+        Action::ShowHelp,
+        "Show the commands and their keys",
+    ),
+    ("close_help", Action::CloseHelp, "Close the help"),
+    (
+        "open_focused_entry",
+        Action::OpenFocusedEntry,
+        "Open the focused entry with its default application",
+    ),
+    (
+        "edit_focused_file",
+        Action::EditFocusedFile,
+        "Edit the focused file with $EDITOR",
+    ),
+    (
+        "open_shell",
+        Action::OpenShell,
+        "Open $SHELL in the focused directory",
+    ),
+];
+
+const NAMED_KEYS: [(&str, KeyCode); 16] = [
+    ("esc", KeyCode::Esc),
+    ("enter", KeyCode::Enter),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("space", KeyCode::Char(' ')),
+];
+
+const MODIFIERS: [(&str, KeyModifiers); 3] = [
+    ("ctrl", KeyModifiers::CONTROL),
+    ("alt", KeyModifiers::ALT),
+    ("shift", KeyModifiers::SHIFT),
+];
+
+/// The name of the action in the config file.
+pub fn action_name(action: &Action) -> &'static str {
+    BINDABLE_ACTIONS
+        .iter()
+        .find(|(_, bindable, _)| mem::discriminant(bindable) == mem::discriminant(action))
+        .map_or("unknown", |(name, _, _)| name)
+}
+
+pub fn action_description(action: &Action) -> &'static str {
+    BINDABLE_ACTIONS
+        .iter()
+        .find(|(_, bindable, _)| mem::discriminant(bindable) == mem::discriminant(action))
+        .map_or("", |(_, _, description)| description)
+}
+
+fn action_by_name(name: &str) -> Option<Action> {
+    BINDABLE_ACTIONS
+        .iter()
+        .find(|(bindable, _, _)| *bindable == name)
+        .map(|(_, action, _)| action.clone())
+}
+
+/// A key together with the modifiers held while pressing it. Characters carry
+/// the shift modifier in their case.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyPress {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyPress {
+    /// Whether the key press types a character.
+    pub fn is_character(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_)) && self.modifiers.is_empty()
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(key: KeyEvent) -> Self {
+        let mut modifiers =
+            key.modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        // SHIFT is reported along with capitalized characters.
+        if let KeyCode::Char(_) | KeyCode::BackTab = key.code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self {
+            code: key.code,
+            modifiers,
+        }
+    }
+}
+
+impl FromStr for KeyPress {
+    type Err = anyhow::Error;
+
+    /// Parse a key like `q`, `enter` or `ctrl-d`.
+    fn from_str(input: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut key = input;
+        // A trailing dash is the key itself.
+        while let Some((modifier, rest)) = key.split_once('-').filter(|(_, rest)| !rest.is_empty())
+        {
+            let Some((_, modifier)) = MODIFIERS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(modifier))
+            else {
+                bail!("Unknown modifier '{modifier}' in '{input}'");
+            };
+            modifiers |= *modifier;
+            key = rest;
+        }
+
+        let mut chars = key.chars();
+        let mut code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match NAMED_KEYS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            {
+                Some((_, code)) => *code,
+                None => match key.strip_prefix(['f', 'F']).map(str::parse) {
+                    Some(Ok(number @ 1..=12)) => KeyCode::F(number),
+                    _ => {
+                        bail!("Unknown key '{key}', the keys of a sequence are separated by spaces")
+                    }
+                },
+            },
+        };
+        if let KeyCode::Char(c) = code {
+            if modifiers.contains(KeyModifiers::SHIFT) {
+                modifiers.remove(KeyModifiers::SHIFT);
+                code = KeyCode::Char(c.to_ascii_uppercase());
+            }
+        }
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, modifier) in MODIFIERS {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{name}-")?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(number) => write!(f, "f{number}"),
+            code => match NAMED_KEYS.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => write!(f, "{name}"),
+                None => write!(f, "{code:?}"),
+            },
+        }
+    }
+}
+
+/// Key presses which run an action together.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeySequence(Vec<KeyPress>);
+
+impl KeySequence {
+    pub fn presses(&self) -> &[KeyPress] {
+        &self.0
+    }
+}
+
+impl From<Vec<KeyPress>> for KeySequence {
+    fn from(presses: Vec<KeyPress>) -> Self {
+        Self(presses)
+    }
+}
+
+impl FromStr for KeySequence {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let presses = input
+            .split_whitespace()
+            .map(str::parse)
+            .collect::<Result<Vec<_>>>()?;
+        if presses.is_empty() {
+            bail!("Empty key");
+        }
+        Ok(Self(presses))
+    }
+}
+
+impl fmt::Display for KeySequence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, press) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{press}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The actions bound to keys on one screen.
+#[derive(Clone)]
+pub struct KeyMap {
+    bindings: Vec<(KeySequence, Action)>,
+}
+
+impl KeyMap {
+    fn from_defaults(defaults: &[(&str, Action)]) -> Self {
+        let bindings = defaults
+            .iter()
+            .map(|(keys, action)| {
+                let keys = keys.parse().expect("The default keys should be valid");
+                (keys, action.clone())
+            })
+            .collect();
+        Self { bindings }
+    }
+
+    /// The action bound to exactly the given key presses.
+    pub fn get(&self, presses: &[KeyPress]) -> Option<&Action> {
+        self.bindings
+            .iter()
+            .find(|(keys, _)| keys.presses() == presses)
+            .map(|(_, action)| action)
+    }
+
+    /// Whether the given key presses start a longer sequence.
+    pub fn starts_sequence(&self, presses: &[KeyPress]) -> bool {
+        self.bindings.iter().any(|(keys, _)| {
+            keys.presses().len() > presses.len() && keys.presses().starts_with(presses)
+        })
+    }
+
+    /// The keys bound to the action, in the order they were bound.
+    pub fn keys_of<'a>(&'a self, action: &'a Action) -> impl Iterator<Item = &'a KeySequence> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| mem::discriminant(bound) == mem::discriminant(action))
+            .map(|(keys, _)| keys)
+    }
+
+    /// The bound actions with their keys, in the order they were first
+    /// bound.
+    pub fn actions(&self) -> Vec<(&Action, Vec<&KeySequence>)> {
+        let mut actions: Vec<(&Action, Vec<&KeySequence>)> = Vec::new();
+        for (keys, action) in &self.bindings {
+            match actions
+                .iter_mut()
+                .find(|(bound, _)| mem::discriminant(*bound) == mem::discriminant(action))
+            {
+                Some((_, bound_keys)) => bound_keys.push(keys),
+                None => actions.push((action, vec![keys])),
+            }
+        }
+        actions
+    }
+
+    /// Replace the keys of the action.
+    fn bind(&mut self, action: Action, keys: Vec<KeySequence>) {
+        self.bindings
+            .retain(|(_, bound)| mem::discriminant(bound) != mem::discriminant(&action));
+        self.bindings
+            .extend(keys.into_iter().map(|keys| (keys, action.clone())));
+    }
+
+    /// Describe the keys which cannot work as bound.
+    fn find_conflicts(&self, allows_sequences: bool, types_characters: bool) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for (index, (keys, action)) in self.bindings.iter().enumerate() {
+            let name = action_name(action);
+            if !allows_sequences && keys.presses().len() > 1 {
+                conflicts.push(format!(
+                    "'{keys}' of {name}: sequences of keys only work on the main screen"
+                ));
+            }
+            if types_characters && keys.presses().iter().any(KeyPress::is_character) {
+                conflicts.push(format!(
+                    "'{keys}' of {name}: characters are typed into the prompt"
+                ));
+            }
+            for (other_keys, other_action) in &self.bindings[index + 1..] {
+                let other_name = action_name(other_action);
+                if keys == other_keys {
+                    conflicts.push(format!("'{keys}' is bound to both {name} and {other_name}"));
+                } else if other_keys.presses().starts_with(keys.presses()) {
+                    conflicts.push(format!(
+                        "'{keys}' of {name} hides '{other_keys}' of {other_name}"
+                    ));
+                } else if keys.presses().starts_with(other_keys.presses()) {
+                    conflicts.push(format!(
+                        "'{other_keys}' of {other_name} hides '{keys}' of {name}"
+                    ));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+/// The key maps of all screens.
+#[derive(Clone)]
+pub struct KeyMaps {
+    pub main_screen: KeyMap,
+    pub confirm_delete_popup: KeyMap,
+    pub deletion_progress_popup: KeyMap,
+    pub deletion_errors_popup: KeyMap,
+    pub traversal_errors: KeyMap,
+    pub filter: KeyMap,
+    pub search: KeyMap,
+    pub search_results: KeyMap,
+    pub help_popup: KeyMap,
+}
+
+impl Default for KeyMaps {
+    fn default() -> Self {
+        let mut main_screen = KeyMap::from_defaults(&DEFAULT_SINGLE_KEY_COMMANDS_MAIN_SCREEN);
+        main_screen
+            .bindings
+            .extend(KeyMap::from_defaults(&DEFAULT_MULTI_KEY_COMMANDS).bindings);
+
+        Self {
+            main_screen,
+            confirm_delete_popup: KeyMap::from_defaults(
+                &DEFAULT_SINGLE_KEY_COMMANDS_CONFIRM_DELETE_POPUP,
+            ),
+            deletion_progress_popup: KeyMap::from_defaults(
+                &DEFAULT_SINGLE_KEY_COMMANDS_DELETION_PROGRESS_POPUP,
+            ),
+            deletion_errors_popup: KeyMap::from_defaults(
+                &DEFAULT_SINGLE_KEY_COMMANDS_DELETION_ERRORS_POPUP,
+            ),
+            traversal_errors: KeyMap::from_defaults(&DEFAULT_SINGLE_KEY_COMMANDS_TRAVERSAL_ERRORS),
+            filter: KeyMap::from_defaults(&DEFAULT_SINGLE_KEY_COMMANDS_FILTER),
+            search: KeyMap::from_defaults(&DEFAULT_SINGLE_KEY_COMMANDS_SEARCH),
+            search_results: KeyMap::from_defaults(&DEFAULT_SINGLE_KEY_COMMANDS_SEARCH_RESULTS),
+            help_popup: KeyMap::from_defaults(&DEFAULT_SINGLE_KEY_COMMANDS_HELP_POPUP),
+        }
+    }
+}
+
+impl KeyMaps {
+    /// Bind the keys of the config on top of the default keys. All the
+    /// mistakes in the config are reported at once.
+    pub fn new(config: &KeysConfig) -> Result<Self> {
+        let mut key_maps = Self::default();
+        let sections = [
+            (
+                "main_screen",
+                &mut key_maps.main_screen,
+                &config.main_screen,
+            ),
+            (
+                "confirm_delete_popup",
+                &mut key_maps.confirm_delete_popup,
+                &config.confirm_delete_popup,
+            ),
+            (
+                "deletion_progress_popup",
+                &mut key_maps.deletion_progress_popup,
+                &config.deletion_progress_popup,
+            ),
+            (
+                "deletion_errors_popup",
+                &mut key_maps.deletion_errors_popup,
+                &config.deletion_errors_popup,
+            ),
+            (
+                "traversal_errors",
+                &mut key_maps.traversal_errors,
+                &config.traversal_errors,
+            ),
+            ("filter", &mut key_maps.filter, &config.filter),
+            ("search", &mut key_maps.search, &config.search),
+            (
+                "search_results",
+                &mut key_maps.search_results,
+                &config.search_results,
+            ),
+            ("help_popup", &mut key_maps.help_popup, &config.help_popup),
+        ];
+
+        let mut errors = Vec::new();
+        for (section, key_map, actions) in sections {
+            // Only the actions handled on the screen can be bound there, the
+            // same ones its default keys are bound to.
+            let available: Vec<_> = key_map
+                .actions()
+                .into_iter()
+                .map(|(action, _)| mem::discriminant(action))
+                .collect();
+            for (name, keys) in actions {
+                let Some(action) = action_by_name(name) else {
+                    errors.push(format!("[keys.{section}] unknown action '{name}'"));
+                    continue;
+                };
+                if !available.contains(&mem::discriminant(&action)) {
+                    errors.push(format!(
+                        "[keys.{section}] action '{name}' is not available here"
+                    ));
+                    continue;
+                }
+                let mut sequences = Vec::new();
+                for keys in keys.as_slice() {
+                    match keys.parse() {
+                        Ok(keys) => sequences.push(keys),
+                        Err(error) => errors.push(format!("[keys.{section}] {name}: {error:#}")),
+                    }
+                }
+                key_map.bind(action, sequences);
+            }
+
+            let allows_sequences = section == "main_screen";
+            let types_characters = matches!(section, "filter" | "search");
+            errors.extend(
+                key_map
+                    .find_conflicts(allows_sequences, types_characters)
+                    .into_iter()
+                    .map(|conflict| format!("[keys.{section}] {conflict}")),
+            );
+        }
+
+        if !errors.is_empty() {
+            bail!("Invalid key bindings:\n  {}", errors.join("\n  "));
+        }
+        Ok(key_maps)
+    }
+}
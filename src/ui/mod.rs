@@ -7,6 +7,12 @@ pub mod event_handling;
 /// Handles received events.
 pub mod disko_event_handling;
 
+/// Keys bound to the application actions.
+pub mod key_map;
+
+/// The configuration file of the application.
+pub mod config;
+
 /// Widget renderer.
 pub mod renderer;
 
@@ -1,36 +1,98 @@
-use std::rc::Rc;
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::{Duration, SystemTime},
+};
 
 use byte_unit::Byte;
+use chrono::{DateTime, Local};
 use ratatui::{
     prelude::*,
     widgets::{block::Title, Block, Borders, Cell, Clear, Paragraph, Row, Table, Wrap},
 };
 
 use crate::backend::{
+    disko_tree::{DeletionMode, TreeSource},
+    duplicates::DuplicateGroup,
     entry_node::{EntryNodeView, EntryType, Mode},
-    entry_size::EntrySize,
+    entry_size::{ChangeKind, EntrySize, SizeChange, SizeMetric},
+    name_pattern::PatternKind,
+    sort_mode::SortMode,
+    tree_walk_state::TraversalError,
 };
 
 use super::{
-    app::{AppFocus, AppState, Main, Preview},
+    app::{Action, AppFocus, AppState, Listing, Main, Preview},
     color_theme::ColorTheme,
-    components::{confirm_delete::ConfirmDeletePopup, table::StatefulTable},
+    components::{
+        confirm_delete::ConfirmDeletePopup, deletion_errors::DeletionErrorsPopup,
+        deletion_progress::DeletionProgressPopup, help::HelpPopup, screen_areas::ScreenAreas,
+        search::SearchResults, table::StatefulTable,
+    },
+    key_map::{KeyMap, KeyMaps},
 };
 
 const BAR_SIZE: usize = 10;
+const ITEMS_SIZE: u16 = 8;
+
+/// The actions hinted at in the bottom panel, with their descriptions.
+const COMMAND_HINTS: [(Action, &str); 13] = [
+    (Action::ShowHelp, "help"),
+    (Action::Quit, "quit"),
+    (Action::ToggleSelection, "select"),
+    (Action::SwitchProgress, "bar"),
+    (Action::ShowConfirmDeletePopup, "delete"),
+    (Action::UndoDeletion, "undo"),
+    (Action::SwitchEntryDisplaySize, "apparent"),
+    (Action::CycleSortMode, "sort"),
+    (Action::ShowFilter, "filter"),
+    (Action::ShowSearch, "find"),
+    (Action::ToggleLargestFiles, "largest"),
+    (Action::FindDuplicates, "duplicates"),
+    (Action::RescanFocusedDirectory, "rescan"),
+];
 
 pub struct Renderer {
     colors: ColorTheme,
+    /// The commands of the main screen with their keys.
+    command_hints: String,
 }
 
 /// The renderer is responsible for rendering widgets in the user interface.
 impl Renderer {
-    pub fn new(colors: ColorTheme) -> Self {
-        Self { colors }
+    pub fn new(colors: ColorTheme, key_maps: &KeyMaps) -> Self {
+        Self {
+            colors,
+            command_hints: Self::get_command_hints(&key_maps.main_screen),
+        }
+    }
+
+    /// List the first key of each hinted action, unbound actions are left
+    /// out.
+    fn get_command_hints(key_map: &KeyMap) -> String {
+        let hints: Vec<String> = COMMAND_HINTS
+            .iter()
+            .filter_map(|(action, description)| {
+                let keys = key_map.keys_of(action).next()?.to_string();
+                let mut chars = keys.chars();
+                // A key which starts the description is shown as part of it,
+                // like q(uit).
+                let hint = match (chars.next(), chars.next()) {
+                    (Some(key), None) if description.starts_with(key.to_ascii_lowercase()) => {
+                        format!("{key}({})", &description[1..])
+                    }
+                    _ => format!("{keys}({description})"),
+                };
+                Some(hint)
+            })
+            .collect();
+        format!("Commands: {}", hints.join(", "))
     }
 
     /// Renders the user interface.
     pub fn render(&self, state: &mut AppState, frame: &mut Frame) {
+        state.areas = ScreenAreas::default();
+
         // Split frame into 3 horizontal chunks.
         let main_chunks = Self::get_main_layout(frame.size());
         // Split the middle horizontal chunks into 2 equal chunks.
@@ -41,16 +103,50 @@ impl Renderer {
         self.render_top_panel(frame, main_chunks[0], top_block, state);
         self.render_bottom_panel(frame, main_chunks[2], bottom_block, state);
 
-        self.render_left_panel(frame, middle_chunks[0], left_block, state);
-        self.render_right_panel(frame, middle_chunks[1], right_block, state);
+        // The help is shown over the screen it was opened on.
+        let screen = match &mut state.focus {
+            AppFocus::Help(help) => help.previous_mut(),
+            focus => focus,
+        };
+        if let AppFocus::TraversalErrors(errors) = screen {
+            self.render_traversal_errors(frame, main_chunks[1], errors);
+        } else if let AppFocus::SearchResults(results) = screen {
+            self.render_search_results(frame, main_chunks[1], results, state.size_metric);
+        } else {
+            self.render_left_panel(frame, middle_chunks[0], left_block, state);
+            self.render_right_panel(frame, middle_chunks[1], right_block, state);
+        }
 
-        match &state.focus {
+        let screen = match &state.focus {
+            AppFocus::Help(help) => help.previous(),
+            focus => focus,
+        };
+        match screen {
             AppFocus::ConfirmDeletePopup(popup) => {
-                let popup_area = Self::get_centered_rect(25, 25, frame.size());
-                self.render_confirm_delete_popup(frame, popup_area, state, popup);
+                let popup_area = Self::get_centered_rect(30, 35, frame.size());
+                let buttons = self.render_confirm_delete_popup(frame, popup_area, state, popup);
+                state.areas.confirm_delete_buttons = Some(buttons);
+            }
+            AppFocus::DeletionProgressPopup(popup) => {
+                let popup_area = Self::get_centered_rect(30, 35, frame.size());
+                self.render_deletion_progress_popup(frame, popup_area, state, popup);
+            }
+            AppFocus::DeletionErrorsPopup(popup) => {
+                let popup_area = Self::get_centered_rect(60, 50, frame.size());
+                self.render_deletion_errors_popup(frame, popup_area, popup);
             }
             AppFocus::MainScreen => (),
+            AppFocus::TraversalErrors(_) => (),
             AppFocus::BufferingInput => (),
+            AppFocus::Filter => (),
+            AppFocus::Search => (),
+            AppFocus::SearchResults(_) => (),
+            AppFocus::Help(_) => (),
+        }
+
+        if let AppFocus::Help(help) = &state.focus {
+            let popup_area = Self::get_centered_rect(70, 70, frame.size());
+            self.render_help_popup(frame, popup_area, help);
         }
     }
 
@@ -128,10 +224,146 @@ impl Renderer {
         block: Block<'_>,
         state: &mut AppState,
     ) {
-        let path = Paragraph::new(state.current_directory.path.display().to_string())
-            .block(block)
+        let mut status = Vec::new();
+        match &state.source {
+            TreeSource::FileSystem => {}
+            TreeSource::NcduImport(path) => status.push(
+                Span::from(format!("imported from {} (read-only)", path.display()))
+                    .style(Style::default().fg(self.colors.highlight)),
+            ),
+            TreeSource::Snapshot(info) => {
+                let age = SystemTime::now()
+                    .duration_since(info.scanned_at)
+                    .unwrap_or_default();
+                let scanned_at = DateTime::<Local>::from(info.scanned_at);
+                status.push(
+                    Span::from(format!(
+                        "snapshot {} old, scanned {} (read-only)",
+                        format_age(age),
+                        scanned_at.format("%d.%m.%Y %H:%M")
+                    ))
+                    .style(
+                        Style::default()
+                            .fg(self.colors.highlight)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                );
+            }
+            TreeSource::Diff { old, new } => {
+                status.push(
+                    Span::from(format!(
+                        "{} → {} (read-only)",
+                        describe_scan(old),
+                        describe_scan(new)
+                    ))
+                    .style(Style::default().fg(self.colors.highlight)),
+                );
+                if let Some(change) = state.current_directory.change {
+                    let delta = change.delta(state.size_metric);
+                    status
+                        .push(Span::from(" • ").style(Style::default().fg(self.colors.secondary)));
+                    status.push(
+                        Span::from(format_delta(delta, state.size_metric)).style(
+                            Style::default().fg(self.change_color(change, state.size_metric)),
+                        ),
+                    );
+                }
+            }
+        }
+        if state.watching {
+            status.push(Span::from("watching").style(Style::default().fg(self.colors.fg)));
+        }
+        if state.excluded_count > 0 {
+            if !status.is_empty() {
+                status.push(Span::from(" • ").style(Style::default().fg(self.colors.secondary)));
+            }
+            status.push(
+                Span::from(format!("{} excluded", state.excluded_count))
+                    .style(Style::default().fg(self.colors.fg)),
+            );
+        }
+        let listing = match &state.listing {
+            Listing::Directory => None,
+            Listing::LargestFiles => Some("largest files".to_string()),
+            Listing::Duplicates(groups) => Some(format!("duplicate groups: {}", groups.len())),
+        };
+        if let Some(listing) = listing {
+            if !status.is_empty() {
+                status.push(Span::from(" • ").style(Style::default().fg(self.colors.secondary)));
+            }
+            status.push(Span::from(listing).style(Style::default().fg(self.colors.highlight)));
+        }
+        if state.filter.is_active() {
+            let (matched, total) = state.filter.counts();
+            if !status.is_empty() {
+                status.push(Span::from(" • ").style(Style::default().fg(self.colors.secondary)));
+            }
+            status.push(
+                Span::from(format!("filtered: {matched} of {total}"))
+                    .style(Style::default().fg(self.colors.highlight)),
+            );
+        }
+        if state.size_metric != SizeMetric::default() {
+            if !status.is_empty() {
+                status.push(Span::from(" • ").style(Style::default().fg(self.colors.secondary)));
+            }
+            status.push(
+                Span::from(state.size_metric.name()).style(Style::default().fg(self.colors.fg)),
+            );
+        }
+        if state.sort_mode != SortMode::default() {
+            if !status.is_empty() {
+                status.push(Span::from(" • ").style(Style::default().fg(self.colors.secondary)));
+            }
+            status.push(
+                Span::from(format!("sorted by {}", state.sort_mode.name()))
+                    .style(Style::default().fg(self.colors.fg)),
+            );
+        }
+        let status = Line::from(status);
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(1),
+                Constraint::Length(status.width() as u16),
+            ])
+            .split(area);
+
+        frame.render_widget(block, area);
+
+        let path = &state.current_directory.path;
+        state.areas.breadcrumbs = Self::get_breadcrumbs(path, chunks[0]);
+        let path = Paragraph::new(path.display().to_string())
             .style(Style::default().fg(self.colors.tertiary));
-        frame.render_widget(path, area);
+        frame.render_widget(path, chunks[0]);
+
+        frame.render_widget(
+            Paragraph::new(status).alignment(Alignment::Right),
+            chunks[1],
+        );
+    }
+
+    /// Get the areas of the path in the top panel which lead to the ancestors
+    /// of the path, each covering the name of its ancestor.
+    fn get_breadcrumbs(path: &Path, area: Rect) -> Vec<(Rect, PathBuf)> {
+        path.ancestors()
+            .skip(1)
+            .filter_map(|ancestor| {
+                let end = Span::from(ancestor.display().to_string()).width();
+                let start = match ancestor.file_name() {
+                    Some(name) => end - Span::from(name.to_string_lossy()).width(),
+                    // The root has no name, its whole path leads to it.
+                    None => 0,
+                };
+                // The path may be cut off at the end of the area.
+                let end = end.min(usize::from(area.width));
+                (start < end).then(|| {
+                    let area = Rect::new(area.x + start as u16, area.y, (end - start) as u16, 1);
+                    (area, ancestor.to_path_buf())
+                })
+            })
+            .collect()
     }
 
     #[cfg(windows)]
@@ -292,31 +524,52 @@ impl Renderer {
 
         frame.render_widget(block, area);
 
-        let root_size = Byte::from_u64(if state.show_disk_size {
-            state.current_directory.sizes.disk_size
-        } else {
-            state.current_directory.sizes.apparent_size
-        })
-        .get_appropriate_unit(byte_unit::UnitType::Decimal);
+        let root_size = format_size(
+            state.current_directory.sizes.get(state.size_metric),
+            state.size_metric,
+        );
         let root_size =
-            Paragraph::new(format!("{root_size:>9.2}",)).style(Style::default().fg(self.colors.fg));
+            Paragraph::new(format!("{root_size:>9}",)).style(Style::default().fg(self.colors.fg));
         frame.render_widget(root_size, left_half_chunks[2]);
 
-        let message =
-            Paragraph::new(state.message.clone()).style(Style::default().fg(self.colors.fg));
-        frame.render_widget(message, left_half_chunks[3]);
-
-        let commands = Paragraph::new("Commands: q(uit), s(elect), b(ar), d(elete), a(pparent)")
-            .style(Style::default().fg(self.colors.fg));
-        frame.render_widget(commands, chunks[1]);
-
-        if !state.traversal_finished {
-            let traversal_indicator = Paragraph::new(state.indicator.render())
-                .style(Style::default().fg(self.colors.highlight))
-                .alignment(Alignment::Right);
+        // The prompt takes the place of both the message and the commands.
+        let prompt_area = left_half_chunks[3].union(chunks[1]);
+        match state.focus {
+            AppFocus::Filter => {
+                let filter = &state.filter;
+                let prompt = self.get_prompt('/', filter.input(), filter.kind(), filter.error());
+                frame.render_widget(prompt, prompt_area);
+            }
+            AppFocus::Search => {
+                let search = &state.search;
+                let prompt = self.get_prompt('?', search.input(), search.kind(), search.error());
+                frame.render_widget(prompt, prompt_area);
+            }
+            _ => {
+                let message = Paragraph::new(state.message.clone())
+                    .style(Style::default().fg(self.colors.fg));
+                frame.render_widget(message, left_half_chunks[3]);
+
+                let commands = Paragraph::new(self.command_hints.as_str())
+                    .style(Style::default().fg(self.colors.fg));
+                frame.render_widget(commands, chunks[1]);
+            }
+        }
 
-            frame.render_widget(traversal_indicator, chunks[2]);
+        let mut status = Vec::new();
+        if state.error_count > 0 {
+            status.push(Span::from(format!("{} errors", state.error_count)));
         }
+        if !state.traversal_finished || state.finding_duplicates {
+            if !status.is_empty() {
+                status.push(Span::from(" "));
+            }
+            status.push(Span::from(state.indicator.render()));
+        }
+        let status = Paragraph::new(Line::from(status))
+            .style(Style::default().fg(self.colors.highlight))
+            .alignment(Alignment::Right);
+        frame.render_widget(status, chunks[2]);
 
         let Main::Table(table) = &state.main else {
             return;
@@ -355,8 +608,21 @@ impl Renderer {
         block: Block<'_>,
         state: &mut AppState,
     ) {
-        match &mut state.main {
-            Main::Table(table) => self.render_table(
+        if let Main::Table(_) = state.main {
+            state.areas.main_table = Some(block.inner(area));
+        }
+
+        match (&mut state.main, &state.listing) {
+            (Main::Table(table), Listing::Duplicates(groups)) => self.render_duplicates(
+                frame,
+                area,
+                block,
+                table,
+                groups,
+                &state.focus,
+                state.size_metric,
+            ),
+            (Main::Table(table), _) => self.render_table(
                 frame,
                 area,
                 block,
@@ -364,9 +630,13 @@ impl Renderer {
                 table,
                 &state.focus,
                 state.show_bar,
-                state.show_disk_size,
+                state.size_metric,
             ),
-            Main::EmptyDirectory => self.render_empty_directory(frame, area, block),
+            (Main::EmptyDirectory, Listing::Duplicates(_)) => {
+                self.render_placeholder(frame, area, block, "No duplicates left");
+            }
+            (Main::EmptyDirectory, _) => self.render_empty_directory(frame, area, block),
+            (Main::NoMatches, _) => self.render_placeholder(frame, area, block, "No matches"),
         }
     }
 
@@ -383,6 +653,7 @@ impl Renderer {
 
         match &mut state.preview {
             Preview::Table(preview_table) => {
+                state.areas.preview_table = Some(block.inner(area));
                 self.render_preview_table(
                     frame,
                     area,
@@ -391,7 +662,7 @@ impl Renderer {
                     preview_table,
                     &state.focus,
                     state.show_bar,
-                    state.show_disk_size,
+                    state.size_metric,
                 );
             }
             Preview::Text(text) => self.render_preview_paragraph(frame, area, block, text),
@@ -400,6 +671,26 @@ impl Renderer {
         }
     }
 
+    fn get_prompt(
+        &self,
+        prefix: char,
+        input: &str,
+        kind: PatternKind,
+        error: Option<&str>,
+    ) -> Paragraph<'_> {
+        let mut prompt = vec![
+            Span::from(format!("{prefix}{input}")).style(Style::default().fg(self.colors.fg)),
+            Span::from(format!(" [{}]", kind.name()))
+                .style(Style::default().fg(self.colors.secondary)),
+        ];
+        if let Some(error) = error {
+            prompt.push(
+                Span::from(format!(" {error}")).style(Style::default().fg(self.colors.highlight)),
+            );
+        }
+        Paragraph::new(Line::from(prompt))
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn render_table(
         &self,
@@ -410,7 +701,7 @@ impl Renderer {
         table_state: &mut StatefulTable<EntryNodeView>,
         app_focus: &AppFocus,
         show_bar: bool,
-        show_disk_size: bool,
+        size_metric: SizeMetric,
     ) {
         let rows = table_state.items.iter().enumerate().map(|(index, data)| {
             let is_focused = table_state.is_focused(index);
@@ -421,20 +712,33 @@ impl Renderer {
 
             Row::new(vec![
                 self.get_selection_cell(is_selected),
-                self.get_name_cell(data.name.clone(), data.entry_type, is_focused, app_focus),
+                self.get_name_cell(data, is_focused, app_focus),
                 self.get_size_progress_cell(
                     data.sizes,
                     total_size,
                     show_bar,
-                    show_disk_size,
+                    size_metric,
                     is_focused,
                     app_focus,
                 ),
-                self.get_size_cell(data.sizes, show_disk_size, is_focused, app_focus),
+                self.get_size_cell(data.sizes, size_metric, is_focused, app_focus),
+                // The change since the earlier scan takes the place of the
+                // items when comparing scans.
+                match data.change {
+                    Some(change) => self.get_delta_cell(change, size_metric, is_focused, app_focus),
+                    None => self.get_items_cell(data, is_focused, app_focus),
+                },
             ])
             .style(self.get_row_style(is_focused, app_focus))
         });
 
+        let last_column = if parent.change.is_some() {
+            // + 1 for the sign
+            Constraint::Length(13)
+        } else {
+            // example: 1234567
+            Constraint::Length(ITEMS_SIZE)
+        };
         let table = Table::default()
             .rows(rows)
             .widths([
@@ -444,15 +748,205 @@ impl Renderer {
                 Constraint::Length(BAR_SIZE as u16 + 2),
                 // + 3 for padding (example: 123.45 KB)
                 Constraint::Length(12),
+                last_column,
             ])
             .block(block);
 
         frame.render_stateful_widget(table, area, &mut table_state.state);
     }
 
+    /// Render the duplicates one group after another, the number of copies
+    /// is shown next to the first file of each group.
+    #[allow(clippy::too_many_arguments)]
+    fn render_duplicates(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        block: Block<'_>,
+        table_state: &mut StatefulTable<EntryNodeView>,
+        groups: &[DuplicateGroup],
+        app_focus: &AppFocus,
+        size_metric: SizeMetric,
+    ) {
+        let group_of = |entry: &EntryNodeView| {
+            groups
+                .iter()
+                .position(|group| group.paths.contains(&entry.path))
+        };
+
+        let mut previous_group = None;
+        let rows: Vec<Row> = table_state
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, data)| {
+                let is_focused = table_state.is_focused(index);
+                let is_selected = table_state.is_selected(index);
+
+                let group = group_of(data);
+                let copies = match group {
+                    Some(group) if previous_group != Some(group) => {
+                        format!("{}×", groups[group].paths.len())
+                    }
+                    _ => String::new(),
+                };
+                previous_group = group;
+
+                let is_highlighted = matches!(
+                    app_focus,
+                    AppFocus::MainScreen | AppFocus::BufferingInput | AppFocus::Filter
+                ) && is_focused;
+                // Tell the neighbouring groups apart by their colors.
+                let fg = if is_highlighted {
+                    self.colors.primary_bg
+                } else if group.is_some_and(|group| group % 2 == 1) {
+                    self.colors.primary
+                } else {
+                    self.colors.fg
+                };
+
+                Row::new(vec![
+                    self.get_selection_cell(is_selected),
+                    Cell::from(Span::styled(
+                        format!("{copies:>4}"),
+                        Style::default().fg(if is_highlighted {
+                            fg
+                        } else {
+                            self.colors.highlight
+                        }),
+                    )),
+                    self.get_size_cell(data.sizes, size_metric, is_focused, app_focus),
+                    Cell::from(Span::styled(data.name.clone(), Style::default().fg(fg))),
+                ])
+                .style(self.get_row_style(is_focused, app_focus))
+            })
+            .collect();
+
+        let table = Table::default()
+            .rows(rows)
+            .widths([
+                Constraint::Length(1),
+                // example: 123×
+                Constraint::Length(4),
+                // + 2 for padding (example: 123.45 KB)
+                Constraint::Length(12),
+                Constraint::Min(10),
+            ])
+            .block(block);
+
+        frame.render_stateful_widget(table, area, &mut table_state.state);
+    }
+
+    fn render_search_results(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        results: &mut SearchResults,
+        size_metric: SizeMetric,
+    ) {
+        let block = Block::default()
+            .title(Title::from(format!(
+                "Search results for '{}' ({})",
+                results.query,
+                results.table.items.len()
+            )))
+            .title_style(Style::default().fg(self.colors.primary))
+            .borders(Borders::TOP | Borders::BOTTOM)
+            .border_style(Style::default().fg(self.colors.secondary));
+
+        if results.table.items.is_empty() {
+            self.render_placeholder(frame, area, block, "No matches");
+            return;
+        }
+
+        let rows = results
+            .table
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let is_focused = results.table.is_focused(index);
+                let fg = if is_focused {
+                    self.colors.primary_bg
+                } else {
+                    self.colors.fg
+                };
+                let mut path = entry
+                    .path
+                    .strip_prefix(&results.root)
+                    .unwrap_or(&entry.path)
+                    .display()
+                    .to_string();
+                if let EntryType::Directory = entry.entry_type {
+                    path.push(std::path::MAIN_SEPARATOR);
+                }
+                Row::new(vec![
+                    Cell::from(Span::styled(
+                        format!(
+                            "{:>10}",
+                            format_size(entry.sizes.get(size_metric), size_metric)
+                        ),
+                        Style::default().fg(fg),
+                    )),
+                    Cell::from(Span::styled(path, Style::default().fg(fg))),
+                ])
+                .style(self.get_row_style(is_focused, &AppFocus::MainScreen))
+            });
+
+        let table = Table::default()
+            .rows(rows)
+            .widths([Constraint::Length(12), Constraint::Min(10)])
+            .block(block);
+
+        frame.render_stateful_widget(table, area, &mut results.table.state);
+    }
+
+    fn render_traversal_errors(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        errors: &mut StatefulTable<TraversalError>,
+    ) {
+        let block = Block::default()
+            .title(Title::from(format!(
+                "Entries which could not be read ({})",
+                errors.items.len()
+            )))
+            .title_style(Style::default().fg(self.colors.primary))
+            .borders(Borders::TOP | Borders::BOTTOM)
+            .border_style(Style::default().fg(self.colors.secondary));
+
+        let rows = errors.items.iter().enumerate().map(|(index, error)| {
+            let is_focused = errors.is_focused(index);
+            let (kind_style, path_style) = if is_focused {
+                (
+                    Style::default().fg(self.colors.primary_bg),
+                    Style::default().fg(self.colors.primary_bg),
+                )
+            } else {
+                (
+                    Style::default().fg(self.colors.highlight),
+                    Style::default().fg(self.colors.fg),
+                )
+            };
+            Row::new(vec![
+                Cell::from(Span::styled(error.kind.to_string(), kind_style)),
+                Cell::from(Span::styled(error.path.display().to_string(), path_style)),
+            ])
+            .style(self.get_row_style(is_focused, &AppFocus::MainScreen))
+        });
+
+        let table = Table::default()
+            .rows(rows)
+            .widths([Constraint::Length(24), Constraint::Min(10)])
+            .block(block);
+
+        frame.render_stateful_widget(table, area, &mut errors.state);
+    }
+
     fn get_row_style(&self, is_focused: bool, app_focus: &AppFocus) -> Style {
         match app_focus {
-            AppFocus::MainScreen | AppFocus::BufferingInput if is_focused => {
+            AppFocus::MainScreen | AppFocus::BufferingInput | AppFocus::Filter if is_focused => {
                 Style::default().bg(self.colors.primary)
             }
             _ => Style::default(),
@@ -469,22 +963,88 @@ impl Renderer {
 
     fn get_name_cell<'a>(
         &self,
-        name: String,
-        entry_type: EntryType,
+        entry: &EntryNodeView,
         is_focused: bool,
         app_focus: &AppFocus,
     ) -> Cell<'a> {
-        let style = match app_focus {
-            AppFocus::MainScreen | AppFocus::BufferingInput if is_focused => {
-                Style::default().fg(self.colors.primary_bg)
-            }
-            _ => match entry_type {
+        let is_highlighted = matches!(
+            app_focus,
+            AppFocus::MainScreen | AppFocus::BufferingInput | AppFocus::Filter
+        ) && is_focused;
+
+        let style = if is_highlighted {
+            Style::default().fg(self.colors.primary_bg)
+        } else {
+            match entry.entry_type {
                 EntryType::Directory => Style::default().fg(self.colors.primary),
                 EntryType::File => Style::default().fg(self.colors.fg),
-            },
+            }
+        };
+        let marker_style = if is_highlighted {
+            style
+        } else {
+            Style::default().fg(self.colors.highlight)
         };
 
-        Cell::from(Text::from(name)).style(style)
+        let mut name = vec![Span::styled(entry.name.clone(), style)];
+        if entry.mount_point {
+            name.push(Span::styled(" (mount point)", marker_style));
+        }
+        if entry.incomplete {
+            name.push(Span::styled(" (incomplete)", marker_style));
+        }
+        if let Some(change) = entry.change {
+            let marker = match change.kind(SizeMetric::default()) {
+                ChangeKind::Added => Some((" (new)", self.colors.added)),
+                ChangeKind::Removed => Some((" (removed)", self.colors.removed)),
+                _ => None,
+            };
+            if let Some((marker, color)) = marker {
+                let style = if is_highlighted {
+                    style
+                } else {
+                    Style::default().fg(color)
+                };
+                name.push(Span::styled(marker, style));
+            }
+        }
+
+        Cell::from(Line::from(name))
+    }
+
+    fn get_delta_cell<'a>(
+        &self,
+        change: SizeChange,
+        size_metric: SizeMetric,
+        is_focused: bool,
+        app_focus: &AppFocus,
+    ) -> Cell<'a> {
+        let fg = match app_focus {
+            AppFocus::MainScreen | AppFocus::BufferingInput | AppFocus::Filter if is_focused => {
+                self.colors.primary_bg
+            }
+            _ => self.change_color(change, size_metric),
+        };
+        let delta = change.delta(size_metric);
+        let delta = if delta == 0 {
+            String::new()
+        } else {
+            format_delta(delta, size_metric)
+        };
+
+        Cell::from(Line::from(vec![
+            Span::from(format!("{delta:>11}")).set_style(Style::default().fg(fg))
+        ]))
+    }
+
+    fn change_color(&self, change: SizeChange, size_metric: SizeMetric) -> Color {
+        match change.kind(size_metric) {
+            ChangeKind::Added => self.colors.added,
+            ChangeKind::Removed => self.colors.removed,
+            ChangeKind::Grown => self.colors.grown,
+            ChangeKind::Shrunk => self.colors.shrunk,
+            ChangeKind::Unchanged => self.colors.secondary,
+        }
     }
 
     fn get_size_progress_cell<'a>(
@@ -492,15 +1052,11 @@ impl Renderer {
         size: EntrySize,
         total_size: EntrySize,
         show_bar: bool,
-        show_disk_size: bool,
+        size_metric: SizeMetric,
         is_focused: bool,
         app_focus: &AppFocus,
     ) -> Cell<'a> {
-        let (size, total_size) = if show_disk_size {
-            (size.disk_size, total_size.disk_size)
-        } else {
-            (size.apparent_size, total_size.apparent_size)
-        };
+        let (size, total_size) = (size.get(size_metric), total_size.get(size_metric));
 
         let rate = (size as f64 / total_size as f64).min(1.0);
         let filled = (rate * BAR_SIZE as f64) as usize;
@@ -519,7 +1075,9 @@ impl Renderer {
         };
 
         let percentages_fg = match app_focus {
-            AppFocus::MainScreen | AppFocus::BufferingInput if is_focused => self.colors.primary_bg,
+            AppFocus::MainScreen | AppFocus::BufferingInput | AppFocus::Filter if is_focused => {
+                self.colors.primary_bg
+            }
             _ => color,
         };
 
@@ -540,27 +1098,47 @@ impl Renderer {
     fn get_size_cell<'a>(
         &self,
         size: EntrySize,
-        show_disk_size: bool,
+        size_metric: SizeMetric,
         is_focused: bool,
         app_focus: &AppFocus,
     ) -> Cell<'a> {
         let fg = match app_focus {
-            AppFocus::MainScreen | AppFocus::BufferingInput if is_focused => self.colors.primary_bg,
+            AppFocus::MainScreen | AppFocus::BufferingInput | AppFocus::Filter if is_focused => {
+                self.colors.primary_bg
+            }
             _ => self.colors.fg,
         };
 
-        let size = if show_disk_size {
-            size.disk_size
-        } else {
-            size.apparent_size
+        Cell::from(Line::from(vec![Span::from(format!(
+            "{:>10}",
+            format_size(size.get(size_metric), size_metric)
+        ))
+        .set_style(Style::default().fg(fg))]))
+    }
+
+    fn get_items_cell<'a>(
+        &self,
+        entry: &EntryNodeView,
+        is_focused: bool,
+        app_focus: &AppFocus,
+    ) -> Cell<'a> {
+        let fg = match app_focus {
+            AppFocus::MainScreen | AppFocus::BufferingInput | AppFocus::Filter if is_focused => {
+                self.colors.primary_bg
+            }
+            _ => self.colors.secondary,
         };
 
-        let appropriate_size =
-            Byte::from_u64(size).get_appropriate_unit(byte_unit::UnitType::Decimal);
+        // Files have no items of their own.
+        let items = match entry.entry_type {
+            EntryType::Directory => entry.descendants_count.to_string(),
+            EntryType::File => String::new(),
+        };
 
         Cell::from(Line::from(vec![Span::from(format!(
-            "{:>10.2}",
-            appropriate_size
+            "{:>width$}",
+            items,
+            width = ITEMS_SIZE as usize
         ))
         .set_style(Style::default().fg(fg))]))
     }
@@ -575,7 +1153,7 @@ impl Renderer {
         table_state: &mut StatefulTable<EntryNodeView>,
         app_focus: &AppFocus,
         show_bar: bool,
-        show_disk_size: bool,
+        size_metric: SizeMetric,
     ) {
         self.render_table(
             frame,
@@ -585,13 +1163,17 @@ impl Renderer {
             table_state,
             app_focus,
             show_bar,
-            show_disk_size,
+            size_metric,
         );
     }
 
     fn render_empty_directory(&self, frame: &mut Frame, area: Rect, block: Block<'_>) {
+        self.render_placeholder(frame, area, block, "Empty directory");
+    }
+
+    fn render_placeholder(&self, frame: &mut Frame, area: Rect, block: Block<'_>, text: &str) {
         let text = Text::styled(
-            "Empty directory",
+            text.to_string(),
             Style::new()
                 .bg(self.colors.fg)
                 .fg(self.colors.primary_bg)
@@ -621,7 +1203,7 @@ impl Renderer {
         area: Rect,
         state: &AppState,
         confirm_delete_popup: &ConfirmDeletePopup,
-    ) {
+    ) -> (Rect, Rect) {
         frame.render_widget(Clear, area);
         let block = Block::default()
             .title(Title::from("Confirm delete"))
@@ -666,7 +1248,7 @@ impl Renderer {
 
         let Main::Table(table) = &state.main else {
             // This should not happen as the popup cannot be opened if the main screen is not a table.
-            return;
+            return (yes_area, no_area);
         };
 
         let selected = table.selected();
@@ -701,11 +1283,231 @@ impl Renderer {
             }
         };
 
-        let text = Paragraph::new(text)
+        let note = match state.deletion_mode {
+            DeletionMode::Trash => Line::styled(
+                "Deleted items will be moved to the trash.",
+                Style::default().fg(self.colors.fg),
+            ),
+            DeletionMode::Permanent => Line::styled(
+                "Deleted items will be gone forever!",
+                Style::default().fg(self.colors.highlight),
+            ),
+        };
+
+        let text = Paragraph::new(vec![Line::from(text), Line::default(), note])
             .style(Style::default().fg(self.colors.fg))
             .wrap(Wrap { trim: true })
             .alignment(Alignment::Center);
 
         frame.render_widget(text, text_area);
+        (yes_area, no_area)
+    }
+
+    fn render_deletion_errors_popup(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        popup: &DeletionErrorsPopup,
+    ) {
+        frame.render_widget(Clear, area);
+        let failures = popup.failures();
+        let title = if failures.len() == 1 {
+            "Could not delete 1 item".to_string()
+        } else {
+            format!("Could not delete {} items", failures.len())
+        };
+        let block = Block::default()
+            .title(Title::from(title))
+            .title_style(Style::default().fg(self.colors.highlight))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.colors.secondary));
+
+        frame.render_widget(block, area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .margin(1)
+            .split(area);
+
+        let lines: Vec<Line> = failures
+            .iter()
+            .skip(popup.scroll())
+            .flat_map(|failure| {
+                [
+                    Line::styled(
+                        failure.path.display().to_string(),
+                        Style::default().fg(self.colors.fg),
+                    ),
+                    Line::styled(
+                        format!("  {}", failure.error),
+                        Style::default().fg(self.colors.highlight),
+                    ),
+                ]
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), layout[0]);
+
+        let close = Span::from("Ok").style(
+            Style::default()
+                .fg(self.colors.primary_bg)
+                .bg(self.colors.primary),
+        );
+        frame.render_widget(
+            Paragraph::new(close).alignment(Alignment::Center),
+            layout[1],
+        );
+    }
+
+    fn render_help_popup(&self, frame: &mut Frame, area: Rect, help: &HelpPopup) {
+        frame.render_widget(Clear, area);
+        let block = Block::default()
+            .title(Title::from("Help"))
+            .title_style(Style::default().fg(self.colors.highlight))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.colors.secondary));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let entries = help.entries();
+        let keys_width = entries
+            .iter()
+            .map(|entry| entry.keys.chars().count())
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<Line> = entries
+            .iter()
+            .skip(help.scroll())
+            .map(|entry| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:keys_width$}  ", entry.keys),
+                        Style::default().fg(self.colors.primary),
+                    ),
+                    Span::styled(entry.description, Style::default().fg(self.colors.fg)),
+                    Span::styled(
+                        format!("  ({})", entry.name),
+                        Style::default().fg(self.colors.secondary),
+                    ),
+                ])
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), inner);
+    }
+
+    fn render_deletion_progress_popup(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        state: &AppState,
+        popup: &DeletionProgressPopup,
+    ) {
+        frame.render_widget(Clear, area);
+        let block = Block::default()
+            .title(Title::from("Deleting"))
+            .title_style(Style::default().fg(self.colors.primary))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.colors.secondary));
+
+        frame.render_widget(block, area);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .margin(1)
+            .split(area);
+
+        let progress = popup.progress();
+        let bytes = state.size_metric.in_bytes();
+        let (freed, total) = (progress.freed.get(bytes), popup.total().get(bytes));
+        let rate = if total == 0 {
+            0.0
+        } else {
+            (freed as f64 / total as f64).min(1.0)
+        };
+        let bar_width = usize::from(layout[0].width.saturating_sub(2));
+        let filled = (rate * bar_width as f64) as usize;
+
+        let text = Paragraph::new(vec![
+            Line::from(format!("Removed {} files", progress.files_removed)),
+            Line::from(format!(
+                "Freed {:.2} of {:.2}",
+                Byte::from_u64(freed).get_appropriate_unit(byte_unit::UnitType::Decimal),
+                Byte::from_u64(total).get_appropriate_unit(byte_unit::UnitType::Decimal),
+            )),
+            Line::default(),
+            Line::from(vec![
+                Span::from("\u{25AC}".repeat(filled))
+                    .style(Style::default().fg(self.colors.primary)),
+                Span::from("\u{25AC}".repeat(bar_width - filled))
+                    .style(Style::default().fg(self.colors.secondary_bg)),
+            ]),
+        ])
+        .style(Style::default().fg(self.colors.fg))
+        .alignment(Alignment::Center);
+        frame.render_widget(text, layout[0]);
+
+        let hint = if popup.cancelling() {
+            Line::styled("Cancelling...", Style::default().fg(self.colors.highlight))
+        } else {
+            Line::styled("Press c to cancel", Style::default().fg(self.colors.fg))
+        };
+        frame.render_widget(Paragraph::new(hint).alignment(Alignment::Center), layout[1]);
+    }
+}
+
+/// Describe where one of the compared scans comes from.
+fn describe_scan(source: &TreeSource) -> String {
+    match source {
+        TreeSource::FileSystem => "live scan".to_string(),
+        TreeSource::NcduImport(path) => path.display().to_string(),
+        TreeSource::Snapshot(info) => format!(
+            "snapshot of {}",
+            DateTime::<Local>::from(info.scanned_at).format("%d.%m.%Y %H:%M")
+        ),
+        TreeSource::Diff { .. } => "comparison".to_string(),
+    }
+}
+
+/// Format the change in size with its sign, e.g. `+1.20 GB`.
+fn format_delta(delta: i128, size_metric: SizeMetric) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    let size = u64::try_from(delta.unsigned_abs()).unwrap_or(u64::MAX);
+    format!("{sign}{}", format_size(size, size_metric))
+}
+
+/// Format the age in its largest whole unit, e.g. `3 days`.
+fn format_age(age: Duration) -> String {
+    const UNITS: [(&str, u64); 5] = [
+        ("year", 365 * 24 * 60 * 60),
+        ("day", 24 * 60 * 60),
+        ("hour", 60 * 60),
+        ("minute", 60),
+        ("second", 1),
+    ];
+    let seconds = age.as_secs();
+    let (unit, length) = UNITS
+        .into_iter()
+        .find(|(_, length)| seconds >= *length)
+        .unwrap_or(("second", 1));
+    let count = seconds / length;
+    if count == 1 {
+        format!("1 {unit}")
+    } else {
+        format!("{count} {unit}s")
+    }
+}
+
+/// Format a size in bytes with an appropriate unit, or a plain inode count.
+fn format_size(size: u64, size_metric: SizeMetric) -> String {
+    match size_metric {
+        SizeMetric::Inodes => size.to_string(),
+        SizeMetric::Apparent | SizeMetric::Disk => format!(
+            "{:.2}",
+            Byte::from_u64(size).get_appropriate_unit(byte_unit::UnitType::Decimal)
+        ),
     }
 }
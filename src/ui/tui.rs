@@ -40,9 +40,6 @@ impl Tui {
     /// It enables the raw mode, sets terminal properties,
     /// and starts the event handler.
     pub fn enter(&mut self) -> Result<()> {
-        crossterm::terminal::enable_raw_mode()?;
-        crossterm::execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
-
         // Define a custom panic hook to reset the terminal properties.
         // This way, you won't have your terminal messed up if an unexpected error happens.
         let panic_hook = panic::take_hook();
@@ -51,6 +48,25 @@ impl Tui {
             panic_hook(panic);
         }));
 
+        self.resume()
+    }
+
+    /// Hands the terminal over to a child process.
+    ///
+    /// The terminal properties are reverted like on exit, and no events are
+    /// read so that the child gets all the input.
+    pub fn suspend(&mut self) -> Result<()> {
+        self.exit()
+    }
+
+    /// Takes the terminal back from a child process.
+    ///
+    /// It sets the terminal properties again, redraws the whole interface
+    /// and restarts the event handler.
+    pub fn resume(&mut self) -> Result<()> {
+        crossterm::terminal::enable_raw_mode()?;
+        crossterm::execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+
         self.terminal.hide_cursor()?;
         self.terminal.clear()?;
 